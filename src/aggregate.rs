@@ -0,0 +1,290 @@
+//! Group by and aggregation
+//!
+//! Rows are grouped by values of key columns and each group is reduced into a single row by
+//! aggregates. Groups keep an order of their first appearance.
+
+use crate::comparator::Comparator;
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::{Value, ValueLimiter, ValueType};
+use crate::vcont::VCont;
+use crate::virtual_data::{Column, VirtualData};
+use std::collections::{HashMap, HashSet};
+
+/// Aggregate function
+///
+/// Empty values are ignored by every function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AggregateFn {
+    /// Count of values
+    Count,
+    /// Count of distinct values
+    CountDistinct,
+    /// Sum of numbers
+    Sum,
+    /// Minimum value by comparator
+    Min,
+    /// Maximum value by comparator
+    Max,
+    /// Mean of numbers as a decimal text
+    ///
+    /// Value only has integer numbers, thus mean is a text of float's display form such as
+    /// "2.5". Whole mean has no fraction such as "3". Mean of no values is an empty value.
+    Mean,
+    /// First value
+    First,
+    /// Last value
+    Last,
+    /// Values joined with a separator
+    Concat(String),
+}
+
+impl AggregateFn {
+    /// Name of function which is used for a default result column name
+    fn name(&self) -> &str {
+        match self {
+            Self::Count => "count",
+            Self::CountDistinct => "count_distinct",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Mean => "mean",
+            Self::First => "first",
+            Self::Last => "last",
+            Self::Concat(_) => "concat",
+        }
+    }
+}
+
+/// Aggregate of a column
+///
+/// Result column is named as "{function}_{column}" unless alias is given.
+///
+/// # Usage
+///
+/// ```rust
+/// use dcsv::{Aggregate, AggregateFn, Comparator};
+///
+/// let aggregates = [
+///     Aggregate::new("price", AggregateFn::Sum).alias("total"),
+///     Aggregate::new("name", AggregateFn::Max).comparator(Comparator::Natural),
+/// ];
+/// ```
+#[derive(Clone, Debug)]
+pub struct Aggregate {
+    pub column: String,
+    pub function: AggregateFn,
+    pub alias: Option<String>,
+    pub comparator: Comparator,
+}
+
+impl Aggregate {
+    /// Create an aggregate of a column
+    ///
+    /// Column can be either column name or column index.
+    pub fn new(column: &str, function: AggregateFn) -> Self {
+        Self {
+            column: column.to_string(),
+            function,
+            alias: None,
+            comparator: Comparator::default(),
+        }
+    }
+
+    /// Set a result column name
+    pub fn alias(mut self, name: &str) -> Self {
+        self.alias = Some(name.to_string());
+        self
+    }
+
+    /// Set a comparator for min and max
+    pub fn comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Get a type of result column
+    pub(crate) fn result_type(&self, source: ValueType) -> ValueType {
+        match self.function {
+            AggregateFn::Count | AggregateFn::CountDistinct | AggregateFn::Sum => ValueType::Number,
+            AggregateFn::Min | AggregateFn::Max | AggregateFn::First | AggregateFn::Last => source,
+            AggregateFn::Mean | AggregateFn::Concat(_) => ValueType::Text,
+        }
+    }
+
+    /// Reduce values of a column into a single value
+    pub(crate) fn apply(&self, column: &Column, values: &[&Value]) -> DcsvResult<Value> {
+        let empty = Value::empty(column.column_type);
+        let column = column.name.as_str();
+        let mut values = values.iter().copied().filter(|v| !v.is_empty());
+        let value = match &self.function {
+            AggregateFn::Count => Value::Number(values.count() as isize),
+            AggregateFn::CountDistinct => {
+                Value::Number(values.collect::<HashSet<_>>().len() as isize)
+            }
+            AggregateFn::Sum => {
+                let mut sum: isize = 0;
+                for value in values {
+                    sum = sum.checked_add(to_number(column, value)?).ok_or_else(|| {
+                        DcsvError::InvalidCellData(format!("Sum of \"{}\" overflows", column))
+                    })?;
+                }
+                Value::Number(sum)
+            }
+            AggregateFn::Min => values
+                .reduce(|min, v| {
+                    if self.comparator.compare(v, min).is_lt() {
+                        v
+                    } else {
+                        min
+                    }
+                })
+                .cloned()
+                .unwrap_or(empty),
+            AggregateFn::Max => values
+                .reduce(|max, v| {
+                    if self.comparator.compare(v, max).is_gt() {
+                        v
+                    } else {
+                        max
+                    }
+                })
+                .cloned()
+                .unwrap_or(empty),
+            AggregateFn::Mean => {
+                let mut sum = 0f64;
+                let mut count = 0usize;
+                for value in values {
+                    sum += to_number(column, value)? as f64;
+                    count += 1;
+                }
+                if count == 0 {
+                    Value::default()
+                } else {
+                    Value::Text((sum / count as f64).to_string())
+                }
+            }
+            AggregateFn::First => values.next().cloned().unwrap_or(empty),
+            AggregateFn::Last => values.next_back().cloned().unwrap_or(empty),
+            AggregateFn::Concat(separator) => Value::Text(
+                values
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(separator),
+            ),
+        };
+        Ok(value)
+    }
+}
+
+/// Convert a value into a number
+fn to_number(column: &str, value: &Value) -> DcsvResult<isize> {
+    match value {
+        Value::Number(num) => Ok(*num),
+        Value::Text(text) => text.trim().parse().map_err(|_| {
+            DcsvError::InvalidCellData(format!(
+                "\"{}\" of column \"{}\" is not a number",
+                text, column
+            ))
+        }),
+    }
+}
+
+impl VirtualData {
+    /// Group rows by columns and aggregate each group into a new virtual data
+    ///
+    /// Result has key columns followed by aggregate columns. Key columns keep their limiters.
+    /// Count, count distinct and sum are numbers, mean and concat are texts and others keep
+    /// source column's type.
+    ///
+    /// Columns can be either column names or column indices. Empty columns aggregate whole rows
+    /// into a single row, even when data has no rows.
+    pub fn group_by(
+        &self,
+        columns: &[impl AsRef<str>],
+        aggregates: &[Aggregate],
+    ) -> DcsvResult<VirtualData> {
+        let keys = if columns.is_empty() {
+            vec![]
+        } else {
+            self.get_column_names(columns)?
+                .iter()
+                .map(|name| self.get_column_position(name))
+                .collect::<DcsvResult<Vec<_>>>()?
+        };
+        let targets = aggregates
+            .iter()
+            .map(|a| {
+                self.try_get_column_index(&a.column).ok_or_else(|| {
+                    DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", a.column))
+                })
+            })
+            .collect::<DcsvResult<Vec<_>>>()?;
+
+        // Build groups from column iterators
+        let key_values = keys
+            .iter()
+            .map(|k| self.get_column_iterator(*k).map(|i| i.collect::<Vec<_>>()))
+            .collect::<DcsvResult<Vec<_>>>()?;
+        let target_values = targets
+            .iter()
+            .map(|t| self.get_column_iterator(*t).map(|i| i.collect::<Vec<_>>()))
+            .collect::<DcsvResult<Vec<_>>>()?;
+        let mut groups: Vec<(Vec<&Value>, Vec<usize>)> = vec![];
+        let mut group_index = HashMap::new();
+        // Whole rows are a single group which exists without rows
+        if keys.is_empty() {
+            groups.push((vec![], vec![]));
+            group_index.insert(vec![], 0);
+        }
+        for row in 0..self.get_row_count() {
+            let key = key_values.iter().map(|v| v[row]).collect::<Vec<_>>();
+            let index = *group_index.entry(key.clone()).or_insert_with(|| {
+                groups.push((key, vec![]));
+                groups.len() - 1
+            });
+            groups[index].1.push(row);
+        }
+
+        let mut data = VirtualData::new();
+        for (index, key) in keys.iter().enumerate() {
+            let column = &self.columns[*key];
+            data.insert_column_with_type(
+                index,
+                &column.name,
+                column.column_type,
+                Some(column.limiter.clone()),
+                None,
+            )?;
+        }
+        for (aggregate, target) in aggregates.iter().zip(&targets) {
+            let column = &self.columns[*target];
+            let name = aggregate
+                .alias
+                .clone()
+                .unwrap_or_else(|| format!("{}_{}", aggregate.function.name(), column.name));
+            let value_type = aggregate.result_type(column.column_type);
+            let mut limiter = ValueLimiter::default();
+            limiter.set_type(value_type);
+            data.insert_column_with_type(
+                data.get_column_count(),
+                &name,
+                value_type,
+                Some(limiter),
+                None,
+            )?;
+        }
+
+        for (index, (key, rows)) in groups.iter().enumerate() {
+            let mut values = key.iter().map(|v| (*v).clone()).collect::<Vec<_>>();
+            for (aggregate, (target, column_values)) in
+                aggregates.iter().zip(targets.iter().zip(&target_values))
+            {
+                let group_values = rows.iter().map(|r| column_values[*r]).collect::<Vec<_>>();
+                values.push(aggregate.apply(&self.columns[*target], &group_values)?);
+            }
+            data.insert_row(index, Some(&values))?;
+        }
+        data.mark_clean();
+        Ok(data)
+    }
+}
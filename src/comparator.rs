@@ -0,0 +1,105 @@
+//! Comparators for values
+//!
+//! Derived ordering of value compares texts byte by byte and puts every number before every text.
+//! Comparators offer other orderings for sorting and aggregation.
+
+use crate::value::Value;
+use std::cmp::Ordering;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// How to compare two values
+///
+/// Two numbers are always compared numerically except for default comparator. Number is
+/// compared as a text with a text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Comparator {
+    /// Derived ordering of value
+    #[default]
+    Default,
+    /// Digits in texts are compared as numbers, e.g. "file2" comes before "file10"
+    Natural,
+    /// Texts are compared in lower case
+    CaseInsensitive,
+    /// Texts are compared by base letters first, then accents and then cases
+    ///
+    /// This is a locale independent approximation of unicode collation.
+    Collation,
+}
+
+impl Comparator {
+    /// Compare two values
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        match (self, a, b) {
+            (Self::Default, _, _) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (_, Value::Number(a), Value::Number(b)) => a.cmp(b),
+            _ => {
+                let (a, b) = (a.to_string(), b.to_string());
+                match self {
+                    Self::Natural => natural_cmp(&a, &b),
+                    Self::CaseInsensitive => a.to_lowercase().cmp(&b.to_lowercase()),
+                    _ => collation_cmp(&a, &b),
+                }
+            }
+        }
+    }
+}
+
+/// Compare texts with digit runs compared as numbers
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_digits(&mut a);
+                let y = take_digits(&mut b);
+                let (xt, yt) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = xt
+                    .len()
+                    .cmp(&yt.len())
+                    .then_with(|| xt.cmp(yt))
+                    // More leading zeros come first
+                    .then_with(|| y.len().cmp(&x.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Consume a run of ascii digits
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Compare texts by base letters, accents and cases in order
+fn collation_cmp(a: &str, b: &str) -> Ordering {
+    let (da, db) = (a.nfd().collect::<String>(), b.nfd().collect::<String>());
+    let base = |s: &str| {
+        s.chars()
+            .filter(|c| !is_combining_mark(*c))
+            .flat_map(|c| c.to_lowercase())
+            .collect::<String>()
+    };
+    base(&da)
+        .cmp(&base(&db))
+        .then_with(|| da.to_lowercase().cmp(&db.to_lowercase()))
+        // Lower case comes before upper case
+        .then_with(|| db.cmp(&da))
+}
@@ -0,0 +1,213 @@
+//! Computed columns
+//!
+//! Computed column gets values from an expression over other columns of a same row. Values are
+//! either materialized once or kept as a derived column which is recomputed on edits.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::expression::{Expression, Resolver};
+use crate::value::{Value, ValueLimiter, ValueType};
+use crate::virtual_data::{Row, VirtualData};
+
+/// Column whose values are recomputed from an expression
+#[derive(Clone, Debug)]
+pub struct DerivedColumn {
+    pub column: String,
+    pub expression: Expression,
+}
+
+impl DerivedColumn {
+    /// Check if derived column depends on a column
+    pub(crate) fn has_column(&self, column: &str) -> bool {
+        self.column == column || self.expression.get_references().contains(&column)
+    }
+
+    /// Rename a column of derived column
+    pub(crate) fn rename_column(&mut self, name: &str, new_name: &str) {
+        if self.column == name {
+            self.column = new_name.to_string();
+        }
+        self.expression.rename_reference(name, new_name);
+    }
+}
+
+/// Resolve references as cells of a row
+struct RowResolver<'a> {
+    row: &'a Row,
+}
+
+impl Resolver for RowResolver<'_> {
+    fn value(&self, reference: &str) -> DcsvResult<Value> {
+        self.row.get_cell_value(reference).cloned().ok_or_else(|| {
+            DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", reference))
+        })
+    }
+
+    fn range(&self, from: &str, to: &str) -> DcsvResult<Vec<Value>> {
+        Err(DcsvError::InvalidExpression(format!(
+            "Range \"{}:{}\" is not allowed in a computed column",
+            from, to
+        )))
+    }
+}
+
+impl VirtualData {
+    /// Insert a column whose values are computed from an expression
+    ///
+    /// Expression refers to other columns by names. Computed values are converted into a column
+    /// type. Derived column is recomputed whenever a row is edited through set_cell, set_row,
+    /// edit_row, set_column or insert_row and cannot be set directly. Otherwise values are
+    /// computed once and the column is an ordinary column.
+    ///
+    /// Data is not modified when any row fails to compute.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use dcsv::{Expression, Reader, ValueType, VCont};
+    ///
+    /// let mut data = Reader::new()
+    ///     .data_from_stream("price,qty\n3,4".as_bytes())
+    ///     .expect("Failed to read data");
+    /// let expression = Expression::parse("price * qty").expect("Failed to parse expression");
+    /// data.insert_computed_column(2, "total", ValueType::Number, &expression, true)
+    ///     .expect("Failed to compute column");
+    /// assert_eq!(data.get_cell(0, 2).unwrap().to_string(), "12");
+    /// ```
+    pub fn insert_computed_column(
+        &mut self,
+        column_index: usize,
+        column_name: &str,
+        column_type: ValueType,
+        expression: &Expression,
+        derived: bool,
+    ) -> DcsvResult<()> {
+        if let Some((from, to)) = expression.get_ranges().first() {
+            return Err(DcsvError::InvalidExpression(format!(
+                "Range \"{}:{}\" is not allowed in a computed column",
+                from, to
+            )));
+        }
+        for reference in expression.get_references() {
+            if !self.columns.iter().any(|c| c.name == reference) {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "Expression refers to a non existent column \"{}\"",
+                    reference
+                )));
+            }
+        }
+
+        let mut limiter = ValueLimiter::default();
+        limiter.set_type(column_type);
+        let definition = DerivedColumn {
+            column: column_name.to_string(),
+            expression: expression.clone(),
+        };
+        // Values are computed before a column is inserted
+        let values = self
+            .rows
+            .iter()
+            .map(|row| compute_value(&definition, &limiter, row))
+            .collect::<DcsvResult<Vec<_>>>()?;
+        self.insert_column_with_type(column_index, column_name, column_type, Some(limiter), None)?;
+        for (index, value) in values.into_iter().enumerate() {
+            self.metas[column_index].update_width_from_value(&value);
+            self.rows[index].update_cell_value(column_name, value);
+        }
+        if derived {
+            self.derived_columns.push(definition);
+        }
+        Ok(())
+    }
+
+    /// Get derived columns in an order of computation
+    pub fn get_derived_columns(&self) -> &[DerivedColumn] {
+        &self.derived_columns
+    }
+
+    /// Check if a column is derived
+    pub fn is_derived_column(&self, column: &str) -> bool {
+        self.derived_columns.iter().any(|d| d.column == column)
+    }
+
+    /// Stop recomputing a derived column
+    ///
+    /// Current values are kept as they are. This returns false when column is not derived.
+    pub fn remove_derivation(&mut self, column: &str) -> bool {
+        let count = self.derived_columns.len();
+        self.derived_columns.retain(|d| d.column != column);
+        count != self.derived_columns.len()
+    }
+
+    /// Recompute every derived column
+    ///
+    /// This is useful after rows are modified directly or by set_limiter. Data is not modified
+    /// when any row fails to compute.
+    pub fn recompute_derived(&mut self) -> DcsvResult<()> {
+        if self.derived_columns.is_empty() {
+            return Ok(());
+        }
+        // Only rows whose derived values change are replaced
+        let mut candidates = vec![];
+        for (index, row) in self.rows.iter().enumerate() {
+            let mut candidate = row.clone();
+            self.derive_row(&mut candidate)?;
+            let changed = self
+                .derived_columns
+                .iter()
+                .any(|d| candidate.get_cell_value(&d.column) != row.get_cell_value(&d.column));
+            if changed {
+                candidates.push((index, candidate, vec![]));
+            }
+        }
+        self.replace_rows(candidates)
+    }
+
+    /// Recompute derived columns of a candidate row
+    ///
+    /// Derived columns are computed in an order of definitions, thus later one can refer to
+    /// former one.
+    pub(crate) fn derive_row(&self, candidate: &mut Row) -> DcsvResult<()> {
+        for derived in &self.derived_columns {
+            let value = self.compute(derived, candidate)?;
+            candidate.update_cell_value(&derived.column, value);
+        }
+        Ok(())
+    }
+
+    /// Update widths of derived columns with values of a row
+    pub(crate) fn update_derived_width(&mut self, row_index: usize) {
+        for derived in &self.derived_columns {
+            if let Some(index) = self.columns.iter().position(|c| c.name == derived.column) {
+                if let Some(value) = self.rows[row_index].get_cell_value(&derived.column) {
+                    self.metas[index].update_width_from_value(value);
+                }
+            }
+        }
+    }
+
+    /// Compute a value of a derived column for a row
+    fn compute(&self, derived: &DerivedColumn, row: &Row) -> DcsvResult<Value> {
+        let column = self
+            .columns
+            .iter()
+            .find(|c| c.name == derived.column)
+            .ok_or_else(|| {
+                DcsvError::InvalidColumn(format!(
+                    "Derived column \"{}\" doesn't exist",
+                    derived.column
+                ))
+            })?;
+        compute_value(derived, &column.limiter, row)
+    }
+}
+
+/// Compute a value of a derived column for a row and conform it to a limiter
+fn compute_value(derived: &DerivedColumn, limiter: &ValueLimiter, row: &Row) -> DcsvResult<Value> {
+    let value = derived.expression.eval(&RowResolver { row })?;
+    limiter.conform(&value).map_err(|rule| {
+        DcsvError::InvalidCellData(format!(
+            "Computed value \"{}\" of column \"{}\" doesn't match limiter's qualification : {}",
+            value, derived.column, rule
+        ))
+    })
+}
@@ -0,0 +1,255 @@
+//! Concatenation of containers
+//!
+//! Rows of another container are appended with columns lined up by names. Columns which only
+//! exist in another container are appended after existing columns.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::validation::{LimiterMode, ValidationReport, Violation};
+use crate::value::{Value, ValueLimiter, ValueType};
+use crate::vcont::VCont;
+use crate::virtual_array::VirtualArray;
+use crate::virtual_data::{Column, Row, VirtualData};
+
+/// Options for concatenation
+///
+/// Mode is only used by virtual data, virtual array ignores it. Source column records where each
+/// row came from. Source column is added when it doesn't exist and existing rows get a tag of
+/// self. Appended rows get a tag of other unless other has the source column.
+///
+/// # Usage
+///
+/// ```rust
+/// use dcsv::{ConcatOption, LimiterMode};
+///
+/// let option = ConcatOption::new()
+///     .mode(LimiterMode::Overwrite)
+///     .source_column("source", "january.csv", "february.csv");
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConcatOption {
+    pub mode: LimiterMode,
+    pub source_column: Option<String>,
+    pub self_tag: String,
+    pub other_tag: String,
+}
+
+impl Default for ConcatOption {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConcatOption {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            mode: LimiterMode::Strict,
+            source_column: None,
+            self_tag: String::new(),
+            other_tag: String::new(),
+        }
+    }
+
+    /// How to treat appended values which don't qualify limiters
+    ///
+    /// - Strict    : Fail without modifying data
+    /// - Overwrite : Replace unqualified values with limiter's default value
+    /// - Collect   : Skip rows with unqualified values
+    pub fn mode(mut self, mode: LimiterMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Record sources of rows in a column
+    pub fn source_column(mut self, column: &str, self_tag: &str, other_tag: &str) -> Self {
+        self.source_column = Some(column.to_string());
+        self.self_tag = self_tag.to_string();
+        self.other_tag = other_tag.to_string();
+        self
+    }
+}
+
+/// Column which has different limiters in both containers
+///
+/// Limiter of self is kept.
+#[derive(Clone, Debug)]
+pub struct LimiterConflict {
+    pub column: String,
+    pub limiter: ValueLimiter,
+    pub other: ValueLimiter,
+}
+
+/// Result of concatenation
+///
+/// Row of a violation is a row index of other container.
+#[derive(Clone, Debug, Default)]
+pub struct ConcatReport {
+    pub conflicts: Vec<LimiterConflict>,
+    pub violations: ValidationReport,
+}
+
+impl VirtualData {
+    /// Append rows of another virtual data with columns lined up by names
+    ///
+    /// Missing values of both sides are filled with column's default value. Values of other
+    /// are converted to limiter's type when possible and unqualified values are treated by mode.
+    /// Key constraints, row validators and enforced foreign keys are checked in every mode.
+    ///
+    /// Data is not modified when concatenation fails.
+    pub fn concat(
+        &mut self,
+        other: &VirtualData,
+        option: &ConcatOption,
+    ) -> DcsvResult<ConcatReport> {
+        let mut report = ConcatReport::default();
+
+        // Columns which only exist in other are appended after existing columns
+        let mut added = vec![];
+        for col in &other.columns {
+            match self.columns.iter().find(|c| c.name == col.name) {
+                Some(own) => {
+                    if own.limiter != col.limiter {
+                        report.conflicts.push(LimiterConflict {
+                            column: col.name.clone(),
+                            limiter: own.limiter.clone(),
+                            other: col.limiter.clone(),
+                        });
+                    }
+                }
+                None => added.push(col.clone()),
+            }
+        }
+        let mut source_added = false;
+        if let Some(source) = &option.source_column {
+            if !self.columns.iter().chain(&added).any(|c| c.name == *source) {
+                added.push(Column::new(source, ValueType::Text, None));
+                source_added = true;
+            }
+        }
+        for (offset, col) in added.iter().enumerate() {
+            // Column name which is an index is treated as an existing column
+            let is_index = col
+                .name
+                .parse::<usize>()
+                .is_ok_and(|index| index < self.get_column_count() + offset);
+            if is_index {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "Cannot add existing column = \"{}\"",
+                    col.name
+                )));
+            }
+        }
+
+        let mut rows = vec![];
+        'rows: for (index, row) in other.rows.iter().enumerate() {
+            let mut candidate = Row::new();
+            for col in self.columns.iter().chain(&added) {
+                let value = match row.get_cell_value(&col.name) {
+                    Some(value) => value,
+                    None if option.source_column.as_ref() == Some(&col.name) => {
+                        candidate.insert_cell(&col.name, Value::Text(option.other_tag.clone()));
+                        continue;
+                    }
+                    None => {
+                        candidate.insert_cell(&col.name, col.get_default_value());
+                        continue;
+                    }
+                };
+                match col.limiter.conform(value) {
+                    Ok(converted) => candidate.insert_cell(&col.name, converted),
+                    Err(rule) => {
+                        if option.mode == LimiterMode::Strict {
+                            return Err(DcsvError::InvalidCellData(format!(
+                                "Cell {},{} of other doesn't match limiter's qualification : {}",
+                                index, col.name, rule
+                            )));
+                        }
+                        report.violations.push(Violation {
+                            row: index,
+                            columns: vec![col.name.clone()],
+                            values: vec![value.clone()],
+                            rule,
+                        });
+                        if option.mode == LimiterMode::Collect {
+                            continue 'rows;
+                        }
+                        candidate.insert_cell(&col.name, col.limiter.get_default_or_empty());
+                    }
+                }
+            }
+            rows.push(candidate);
+        }
+        self.check_appended_rows(&mut rows)?;
+
+        // Rows are validated above, thus following operations don't fail
+        let last = added.len().saturating_sub(1);
+        for (offset, col) in added.into_iter().enumerate() {
+            let index = self.get_column_count();
+            let placeholder =
+                (source_added && offset == last).then(|| Value::Text(option.self_tag.clone()));
+            self.insert_column_with_type(
+                index,
+                &col.name,
+                col.column_type,
+                Some(col.limiter),
+                placeholder,
+            )?;
+            self.columns[index].description = col.description;
+            self.columns[index].unit = col.unit;
+        }
+        self.append_rows(rows);
+        Ok(report)
+    }
+}
+
+impl VirtualArray {
+    /// Append rows of another virtual array with columns lined up by names
+    ///
+    /// Duplicate column name refers to a first column with the name. Missing values of both
+    /// sides are filled with empty values.
+    ///
+    /// Only source column of option is used. Array doesn't have limiters, thus mode of option is
+    /// ignored and every value is appended as it is. This is why array's concat doesn't fail nor
+    /// return a report of conflicts and violations.
+    pub fn concat(&mut self, other: &VirtualArray, option: &ConcatOption) {
+        for col in &other.columns {
+            if !self.columns.iter().any(|c| c.name == col.name) {
+                // It is safe to unwrap because index is a column count
+                self.insert_column(self.get_column_count(), &col.name)
+                    .unwrap();
+            }
+        }
+        let mut source_index = None;
+        if let Some(source) = &option.source_column {
+            source_index = self.columns.iter().position(|c| c.name == *source);
+            if source_index.is_none() {
+                let index = self.get_column_count();
+                // It is safe to unwrap because index is a column count
+                self.insert_column(index, source).unwrap();
+                for row in &mut self.rows {
+                    row[index] = Value::Text(option.self_tag.clone());
+                }
+                source_index = Some(index);
+            }
+        }
+
+        let positions = self
+            .columns
+            .iter()
+            .map(|c| other.columns.iter().position(|o| o.name == c.name))
+            .collect::<Vec<_>>();
+        for row in &other.rows {
+            let values = positions
+                .iter()
+                .enumerate()
+                .map(|(index, position)| match position {
+                    Some(position) => row[*position].clone(),
+                    None if source_index == Some(index) => Value::Text(option.other_tag.clone()),
+                    None => Value::default(),
+                })
+                .collect::<Vec<_>>();
+            self.rows.push(values);
+        }
+        self.update_width_global();
+    }
+}
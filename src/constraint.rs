@@ -0,0 +1,310 @@
+//! Key constraints for virtual data
+//!
+//! Key constraint guarantees that values of given columns are unique across rows. Each constraint
+//! keeps an index of keys so that an edit doesn't need to iterate every row.
+//!
+//! Foreign key refers to key columns of another virtual data. Referenced keys are kept as a
+//! snapshot which should be synchronized when the referenced data changes.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::Value;
+use crate::virtual_data::Row;
+use std::collections::{HashMap, HashSet};
+
+/// Kind of a key constraint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyKind {
+    /// Key should be unique. Key which is composed of only empty values is not checked.
+    Unique,
+    /// Key should be unique and cannot contain an empty value. Data can have only one primary key.
+    PrimaryKey,
+}
+
+impl std::fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Unique => "unique",
+                Self::PrimaryKey => "primary key",
+            }
+        )
+    }
+}
+
+/// Unique or primary key constraint over one or more columns
+#[derive(Clone, Debug)]
+pub struct KeyConstraint {
+    kind: KeyKind,
+    columns: Vec<String>,
+    // Key to occurrence count
+    index: HashMap<Vec<Value>, usize>,
+}
+
+impl KeyConstraint {
+    /// Create a new constraint with an empty index
+    pub(crate) fn new(kind: KeyKind, columns: Vec<String>) -> Self {
+        Self {
+            kind,
+            columns,
+            index: HashMap::new(),
+        }
+    }
+
+    /// Get kind of constraint
+    pub fn get_kind(&self) -> KeyKind {
+        self.kind
+    }
+
+    /// Get column names of constraint
+    pub fn get_columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Check if constraint includes a column
+    pub fn has_column(&self, name: &str) -> bool {
+        self.columns.iter().any(|c| c == name)
+    }
+
+    /// Check if key exists in data
+    pub fn contains(&self, key: &[Value]) -> bool {
+        self.index.get(key).is_some_and(|count| *count > 0)
+    }
+
+    /// Get a key of a row
+    ///
+    /// This returns none when a key is not a subject of the constraint.
+    pub(crate) fn key_of(&self, row: &Row) -> Option<Vec<Value>> {
+        let key = self
+            .columns
+            .iter()
+            .map(|c| row.get_cell_value(c).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+        if self.kind == KeyKind::Unique && key.iter().all(|v| v.is_empty()) {
+            return None;
+        }
+        Some(key)
+    }
+
+    /// Check if a row can replace a previous row without violating constraint
+    pub(crate) fn check(&self, previous: Option<&Row>, candidate: &Row) -> DcsvResult<()> {
+        let key = match self.key_of(candidate) {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        if self.kind == KeyKind::PrimaryKey && key.iter().any(|v| v.is_empty()) {
+            return Err(DcsvError::ConstraintViolation(format!(
+                "Primary key ({}) cannot have an empty value",
+                self.columns.join(",")
+            )));
+        }
+        // Unchanged key doesn't collide with itself
+        if previous.and_then(|p| self.key_of(p)).as_ref() == Some(&key) {
+            return Ok(());
+        }
+        if self.contains(&key) {
+            return Err(DcsvError::ConstraintViolation(format!(
+                "Key ({}) = ({}) violates {} constraint",
+                self.columns.join(","),
+                key.iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                self.kind
+            )));
+        }
+        Ok(())
+    }
+
+    /// Add a row's key to index
+    pub(crate) fn insert(&mut self, row: &Row) {
+        if let Some(key) = self.key_of(row) {
+            *self.index.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    /// Remove a row's key from index
+    pub(crate) fn remove(&mut self, row: &Row) {
+        if let Some(key) = self.key_of(row) {
+            if let Some(count) = self.index.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.index.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Build index from rows
+    ///
+    /// This fails when rows violate the constraint.
+    pub(crate) fn build(&mut self, rows: &[Row]) -> DcsvResult<()> {
+        self.index.clear();
+        for row in rows {
+            self.check(None, row)?;
+            self.insert(row);
+        }
+        Ok(())
+    }
+
+    /// Build index from rows without checking violations
+    pub(crate) fn rebuild(&mut self, rows: &[Row]) {
+        self.index.clear();
+        for row in rows {
+            self.insert(row);
+        }
+    }
+
+    /// Rename a column of constraint
+    pub(crate) fn rename_column(&mut self, name: &str, new_name: &str) {
+        for col in &mut self.columns {
+            if col == name {
+                *col = new_name.to_string();
+            }
+        }
+    }
+}
+
+/// Foreign key constraint which refers to columns of another virtual data
+///
+/// Foreign key doesn't borrow referenced data but keeps a snapshot of referenced keys. Key
+/// composed of only empty values is not a reference and is never dangling.
+///
+/// Keys are compared by text form, thus a number 1 refers to a text "1". Snapshot is not updated
+/// when referenced data changes, thus sync the foreign key after editing referenced data.
+#[derive(Clone, Debug)]
+pub struct ForeignKey {
+    name: String,
+    columns: Vec<String>,
+    referenced_columns: Vec<String>,
+    enforce: bool,
+    // Referenced keys in text form
+    referenced_keys: HashSet<Vec<String>>,
+}
+
+impl ForeignKey {
+    /// Create a new foreign key with an empty snapshot
+    pub(crate) fn new(
+        name: &str,
+        columns: Vec<String>,
+        referenced_columns: Vec<String>,
+        enforce: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            columns,
+            referenced_columns,
+            enforce,
+            referenced_keys: HashSet::new(),
+        }
+    }
+
+    /// Get foreign key name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get referencing column names
+    pub fn get_columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Get referenced column names
+    pub fn get_referenced_columns(&self) -> &[String] {
+        &self.referenced_columns
+    }
+
+    /// Check if edits which break a reference are refused
+    pub fn is_enforced(&self) -> bool {
+        self.enforce
+    }
+
+    /// Check if foreign key includes a column
+    pub fn has_column(&self, name: &str) -> bool {
+        self.columns.iter().any(|c| c == name)
+    }
+
+    /// Get a referencing key of a row
+    ///
+    /// This returns none when a key is not a reference.
+    pub(crate) fn key_of(&self, row: &Row) -> Option<Vec<Value>> {
+        let key = self
+            .columns
+            .iter()
+            .map(|c| row.get_cell_value(c).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+        if key.iter().all(|v| v.is_empty()) {
+            return None;
+        }
+        Some(key)
+    }
+
+    /// Get a referencing key of a row which doesn't exist in a snapshot
+    pub(crate) fn dangling_key(&self, row: &Row) -> Option<Vec<Value>> {
+        self.key_of(row)
+            .filter(|key| !self.referenced_keys.contains(&text_key(key)))
+    }
+
+    /// Check if a row refers to an existing key of a snapshot
+    pub(crate) fn check(&self, row: &Row) -> DcsvResult<()> {
+        if let Some(key) = self.dangling_key(row) {
+            return Err(DcsvError::ConstraintViolation(format!(
+                "Key ({}) = ({}) doesn't exist in referenced columns ({}) of foreign key \"{}\"",
+                self.columns.join(","),
+                key.iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                self.referenced_columns.join(","),
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Replace snapshot of referenced keys
+    pub(crate) fn sync(&mut self, keys: HashSet<Vec<String>>) {
+        self.referenced_keys = keys;
+    }
+
+    /// Rename a referencing column
+    pub(crate) fn rename_column(&mut self, name: &str, new_name: &str) {
+        for col in &mut self.columns {
+            if col == name {
+                *col = new_name.to_string();
+            }
+        }
+    }
+}
+
+/// Convert a key into text form
+pub(crate) fn text_key(key: &[Value]) -> Vec<String> {
+    key.iter().map(|v| v.to_string()).collect()
+}
+
+/// Reference which doesn't exist in referenced data
+#[derive(Clone, Debug)]
+pub struct DanglingReference {
+    pub row: usize,
+    pub foreign_key: String,
+    pub columns: Vec<String>,
+    pub key: Vec<Value>,
+}
+
+impl std::fmt::Display for DanglingReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Row {} has dangling reference ({}) = ({}) of foreign key \"{}\"",
+            self.row,
+            self.columns.join(","),
+            self.key
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            self.foreign_key
+        )
+    }
+}
@@ -0,0 +1,184 @@
+//! Deduplication of rows
+//!
+//! Rows are duplicates when they have equal values on given columns. Every column is compared
+//! when no column is given, which finds exact duplicates. Empty values are equal to each other.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::meta::Meta;
+use crate::value::Value;
+use crate::vcont::VCont;
+use crate::virtual_array::VirtualArray;
+use crate::virtual_data::VirtualData;
+use std::collections::{HashMap, HashSet};
+use unicode_width::UnicodeWidthStr;
+
+/// Which row of duplicates to keep
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Keep {
+    /// Keep a first row
+    #[default]
+    First,
+    /// Keep a last row
+    Last,
+    /// Remove every duplicate row
+    None,
+}
+
+/// Group row indices by keys
+///
+/// Only groups with more than one row are returned. Groups keep an order of their first
+/// appearance.
+fn duplicate_groups(keys: Vec<Vec<&Value>>) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut group_index = HashMap::new();
+    for (row, key) in keys.into_iter().enumerate() {
+        let index = *group_index.entry(key).or_insert_with(|| {
+            groups.push(vec![]);
+            groups.len() - 1
+        });
+        groups[index].push(row);
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+/// Get row indices to be removed from duplicate groups
+fn removed_rows(groups: &[Vec<usize>], keep: Keep) -> HashSet<usize> {
+    groups
+        .iter()
+        .flat_map(|group| {
+            let kept = match keep {
+                Keep::First => group.first(),
+                Keep::Last => group.last(),
+                Keep::None => None,
+            };
+            group.iter().copied().filter(move |row| Some(row) != kept)
+        })
+        .collect()
+}
+
+/// Create metas with widths of column names
+fn name_metas<'a>(names: impl Iterator<Item = &'a str>) -> Vec<Meta> {
+    names
+        .map(|name| {
+            let mut meta = Meta::new();
+            meta.set_width(UnicodeWidthStr::width(name));
+            meta
+        })
+        .collect()
+}
+
+impl VirtualData {
+    /// Get groups of row indices which have equal values on given columns
+    ///
+    /// Columns can be either column names or column indices. Every column is compared when
+    /// columns are empty.
+    pub fn duplicates(&self, columns: &[impl AsRef<str>]) -> DcsvResult<Vec<Vec<usize>>> {
+        let names = if columns.is_empty() {
+            self.columns.iter().map(|c| c.name.clone()).collect()
+        } else {
+            self.get_column_names(columns)?
+        };
+        let keys = self
+            .rows
+            .iter()
+            .map(|row| {
+                names
+                    .iter()
+                    .map(|name| {
+                        row.get_cell_value(name).ok_or_else(|| {
+                            DcsvError::InvalidRowData(
+                                "Failed to get row data while finding duplicates".to_string(),
+                            )
+                        })
+                    })
+                    .collect::<DcsvResult<Vec<_>>>()
+            })
+            .collect::<DcsvResult<Vec<_>>>()?;
+        Ok(duplicate_groups(keys))
+    }
+
+    /// Remove duplicate rows and return a count of removed rows
+    ///
+    /// Columns can be either column names or column indices. Every column is compared when
+    /// columns are empty. Remaining rows keep their order.
+    pub fn dedup(&mut self, columns: &[impl AsRef<str>], keep: Keep) -> DcsvResult<usize> {
+        let removed = removed_rows(&self.duplicates(columns)?, keep);
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        let mut index = 0;
+        self.rows.retain(|_| {
+            index += 1;
+            !removed.contains(&(index - 1))
+        });
+        self.changes.remove_rows(&removed);
+        self.rebuild_key_indices();
+        self.metas = name_metas(self.columns.iter().map(|c| c.name.as_str()));
+        self.update_width_global();
+        Ok(removed.len())
+    }
+}
+
+impl VirtualArray {
+    /// Get groups of row indices which have equal values on given columns
+    ///
+    /// Columns can be either column names or column indices. Duplicate column name refers to a
+    /// first column with the name. Every column is compared when columns are empty.
+    pub fn duplicates(&self, columns: &[impl AsRef<str>]) -> DcsvResult<Vec<Vec<usize>>> {
+        let indices = if columns.is_empty() {
+            (0..self.get_column_count()).collect()
+        } else {
+            columns
+                .iter()
+                .map(|column| {
+                    let column = column.as_ref();
+                    match column.parse::<usize>() {
+                        Ok(index) if index < self.get_column_count() => Some(index),
+                        Ok(_) => None,
+                        Err(_) => self.columns.iter().position(|c| c.name == column),
+                    }
+                    .ok_or_else(|| {
+                        DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", column))
+                    })
+                })
+                .collect::<DcsvResult<Vec<_>>>()?
+        };
+        let keys = self
+            .rows
+            .iter()
+            .map(|row| indices.iter().map(|c| &row[*c]).collect())
+            .collect();
+        Ok(duplicate_groups(keys))
+    }
+
+    /// Remove duplicate rows and return a count of removed rows
+    ///
+    /// Columns can be either column names or column indices. Every column is compared when
+    /// columns are empty. Remaining rows keep their order.
+    pub fn dedup(&mut self, columns: &[impl AsRef<str>], keep: Keep) -> DcsvResult<usize> {
+        let removed = removed_rows(&self.duplicates(columns)?, keep);
+        if removed.is_empty() {
+            return Ok(0);
+        }
+        let mut index = 0;
+        self.rows.retain(|_| {
+            index += 1;
+            !removed.contains(&(index - 1))
+        });
+        self.changes.remove_rows(&removed);
+        self.metas = name_metas(self.columns.iter().map(|c| c.name.as_str()));
+        self.remap_formulas(
+            |row| {
+                if removed.contains(&row) {
+                    None
+                } else {
+                    Some(row - removed.iter().filter(|r| **r < row).count())
+                }
+            },
+            Some,
+        );
+        self.update_width_global();
+        Ok(removed.len())
+    }
+}
@@ -0,0 +1,410 @@
+//! Keyed data diff
+//!
+//! Diff matches rows of a source and a target by key columns and compares cells of columns which
+//! exist in both. Columns are matched by names and cells are compared by their text forms, thus
+//! a number and a text of a same form are equal.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::utils::escape_field;
+use crate::value::Value;
+use crate::vcont::VCont;
+use crate::virtual_array::VirtualArray;
+use crate::virtual_data::{Column, VirtualData};
+use std::collections::HashMap;
+
+/// A single change between two data
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataChange {
+    /// Column only exists in target
+    ColumnAdded(String),
+    /// Column only exists in source
+    ColumnRemoved(String),
+    /// Column has a different position among columns which exist in both data
+    ColumnMoved {
+        column: String,
+        from: usize,
+        to: usize,
+    },
+    /// Row only exists in target. Row is an index of target.
+    RowAdded { key: Vec<Value>, row: usize },
+    /// Row only exists in source. Row is an index of source.
+    RowRemoved { key: Vec<Value>, row: usize },
+    /// Cell has a different value
+    CellChanged {
+        key: Vec<Value>,
+        column: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl DataChange {
+    fn key(&self) -> Option<&[Value]> {
+        match self {
+            Self::RowAdded { key, .. }
+            | Self::RowRemoved { key, .. }
+            | Self::CellChanged { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Get a kind of change as it is written in csv report
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::ColumnAdded(_) => "column_added",
+            Self::ColumnRemoved(_) => "column_removed",
+            Self::ColumnMoved { .. } => "column_moved",
+            Self::RowAdded { .. } => "row_added",
+            Self::RowRemoved { .. } => "row_removed",
+            Self::CellChanged { .. } => "cell_changed",
+        }
+    }
+}
+
+fn key_to_string(key: &[Value]) -> String {
+    format!(
+        "({})",
+        key.iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+impl std::fmt::Display for DataChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ColumnAdded(column) => write!(f, "+ column {}", column),
+            Self::ColumnRemoved(column) => write!(f, "- column {}", column),
+            Self::ColumnMoved { column, from, to } => {
+                write!(f, "~ column {} : {} -> {}", column, from, to)
+            }
+            Self::RowAdded { key, .. } => write!(f, "+ row {}", key_to_string(key)),
+            Self::RowRemoved { key, .. } => write!(f, "- row {}", key_to_string(key)),
+            Self::CellChanged {
+                key,
+                column,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ row {} {} : \"{}\" -> \"{}\"",
+                key_to_string(key),
+                column,
+                old,
+                new
+            ),
+        }
+    }
+}
+
+/// Difference between two data
+///
+/// Column changes come first. Removed rows follow in an order of source and then added rows and
+/// changed cells follow in an order of target.
+#[derive(Clone, Debug, Default)]
+pub struct DataDiff {
+    pub changes: Vec<DataChange>,
+    /// Names of key columns
+    pub key_columns: Vec<String>,
+}
+
+impl DataDiff {
+    /// Check if data have no difference
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Get count of changes
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Get iterator of changes
+    pub fn iter(&self) -> std::slice::Iter<'_, DataChange> {
+        self.changes.iter()
+    }
+
+    /// Get a csv formatted report
+    ///
+    /// Columns are "change", key columns, "column", "old" and "new". Fields which are not
+    /// relevant to a change are empty. Old and new of a moved column are positions.
+    pub fn to_csv(&self) -> String {
+        let mut header = vec!["change".to_string()];
+        header.extend(self.key_columns.iter().cloned());
+        header.extend(["column", "old", "new"].map(String::from));
+        let mut lines = vec![header];
+        for change in &self.changes {
+            let mut line = vec![change.kind().to_string()];
+            match change.key() {
+                Some(key) => line.extend(key.iter().map(|v| v.to_string())),
+                None => line.extend(self.key_columns.iter().map(|_| String::new())),
+            }
+            let (column, old, new) = match change {
+                DataChange::ColumnAdded(column) | DataChange::ColumnRemoved(column) => {
+                    (column.clone(), String::new(), String::new())
+                }
+                DataChange::ColumnMoved { column, from, to } => {
+                    (column.clone(), from.to_string(), to.to_string())
+                }
+                DataChange::RowAdded { .. } | DataChange::RowRemoved { .. } => {
+                    (String::new(), String::new(), String::new())
+                }
+                DataChange::CellChanged {
+                    column, old, new, ..
+                } => (column.clone(), old.to_string(), new.to_string()),
+            };
+            line.extend([column, old, new]);
+            lines.push(line);
+        }
+        lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|field| escape_field(field))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl std::fmt::Display for DataDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .changes
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Resolve key columns of either names or indices into names
+fn key_names(columns: &[Column], key_columns: &[impl AsRef<str>]) -> DcsvResult<Vec<String>> {
+    if key_columns.is_empty() {
+        return Err(DcsvError::InvalidColumn(
+            "Diff requires at least one key column".to_string(),
+        ));
+    }
+    key_columns
+        .iter()
+        .map(|column| {
+            let column = column.as_ref();
+            // Names come first, thus a numeric header is not misread as an index
+            columns
+                .iter()
+                .find(|c| c.name == column)
+                .or_else(|| {
+                    column
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| columns.get(index))
+                })
+                .map(|c| c.name.clone())
+                .ok_or_else(|| {
+                    DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", column))
+                })
+        })
+        .collect()
+}
+
+/// Index rows by keys and fail on a duplicate key
+fn index_keys(
+    data: &impl VCont,
+    key_indices: &[usize],
+    side: &str,
+) -> DcsvResult<HashMap<Vec<String>, usize>> {
+    let mut rows = HashMap::new();
+    for row in 0..data.get_row_count() {
+        let key = key_indices
+            .iter()
+            .map(|column| {
+                data.get_cell(row, *column)
+                    .map(|v| v.to_string())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        if rows.insert(key.clone(), row).is_some() {
+            return Err(DcsvError::InvalidRowData(format!(
+                "Key ({}) is duplicated in {} data",
+                key.join(", "),
+                side
+            )));
+        }
+    }
+    Ok(rows)
+}
+
+fn get_key(data: &impl VCont, row: usize, key_indices: &[usize]) -> Vec<Value> {
+    key_indices
+        .iter()
+        .map(|column| data.get_cell(row, *column).cloned().unwrap_or_default())
+        .collect()
+}
+
+/// Compare two containers by key columns
+fn diff(
+    source: &impl VCont,
+    source_columns: &[Column],
+    target: &impl VCont,
+    target_columns: &[Column],
+    key_columns: &[impl AsRef<str>],
+) -> DcsvResult<DataDiff> {
+    let keys = key_names(source_columns, key_columns)?;
+    let position = |columns: &[Column], name: &str| columns.iter().position(|c| c.name == name);
+    let mut source_keys = vec![];
+    let mut target_keys = vec![];
+    for key in &keys {
+        // It is safe to unwrap because key names are resolved from source columns
+        source_keys.push(position(source_columns, key).unwrap());
+        target_keys.push(position(target_columns, key).ok_or_else(|| {
+            DcsvError::InvalidColumn(format!("Key column \"{}\" doesn't exist in target", key))
+        })?);
+    }
+
+    let mut changes = vec![];
+    // Columns
+    for column in target_columns {
+        if position(source_columns, &column.name).is_none() {
+            changes.push(DataChange::ColumnAdded(column.name.clone()));
+        }
+    }
+    for column in source_columns {
+        if position(target_columns, &column.name).is_none() {
+            changes.push(DataChange::ColumnRemoved(column.name.clone()));
+        }
+    }
+    let common = |from: &[Column], to: &[Column]| {
+        from.iter()
+            .filter(|c| position(to, &c.name).is_some())
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>()
+    };
+    let source_common = common(source_columns, target_columns);
+    let target_common = common(target_columns, source_columns);
+    for (to, column) in target_common.iter().enumerate() {
+        // It is safe to unwrap because both have same columns
+        let from = source_common.iter().position(|c| c == column).unwrap();
+        if from != to {
+            changes.push(DataChange::ColumnMoved {
+                column: column.clone(),
+                from,
+                to,
+            });
+        }
+    }
+
+    // Rows
+    let source_rows = index_keys(source, &source_keys, "source")?;
+    let target_rows = index_keys(target, &target_keys, "target")?;
+    let mut removed = source_rows
+        .iter()
+        .filter(|(key, _)| !target_rows.contains_key(*key))
+        .map(|(_, row)| *row)
+        .collect::<Vec<_>>();
+    removed.sort_unstable();
+    for row in removed {
+        changes.push(DataChange::RowRemoved {
+            key: get_key(source, row, &source_keys),
+            row,
+        });
+    }
+    let compared = source_common
+        .iter()
+        .filter(|c| !keys.contains(c))
+        .map(|c| {
+            // It is safe to unwrap because both have common columns
+            (
+                c,
+                position(source_columns, c).unwrap(),
+                position(target_columns, c).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut target_order = target_rows.iter().collect::<Vec<_>>();
+    target_order.sort_unstable_by_key(|(_, row)| **row);
+    for (key, &row) in target_order {
+        let key_values = get_key(target, row, &target_keys);
+        let Some(&source_row) = source_rows.get(key) else {
+            changes.push(DataChange::RowAdded {
+                key: key_values,
+                row,
+            });
+            continue;
+        };
+        for (column, source_index, target_index) in &compared {
+            let old = source
+                .get_cell(source_row, *source_index)
+                .cloned()
+                .unwrap_or_default();
+            let new = target
+                .get_cell(row, *target_index)
+                .cloned()
+                .unwrap_or_default();
+            if old.to_string() != new.to_string() {
+                changes.push(DataChange::CellChanged {
+                    key: key_values.clone(),
+                    column: column.to_string(),
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+
+    Ok(DataDiff {
+        changes,
+        key_columns: keys,
+    })
+}
+
+impl VirtualData {
+    /// Compare data with a target data by key columns
+    ///
+    /// Key columns can be either column names or column indices of source and should exist in
+    /// both data. Name is matched first, thus a numeric header is not treated as an index.
+    /// Duplicate key in either data is an error.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use dcsv::Reader;
+    ///
+    /// let old = Reader::new()
+    ///     .data_from_stream("id,name\n1,a\n2,b".as_bytes())
+    ///     .expect("Failed to read data");
+    /// let new = Reader::new()
+    ///     .data_from_stream("id,name\n2,c\n3,d".as_bytes())
+    ///     .expect("Failed to read data");
+    /// let diff = old.diff(&new, &["id"]).expect("Failed to diff data");
+    /// assert_eq!(
+    ///     diff.to_string(),
+    ///     "- row (1)\n~ row (2) name : \"b\" -> \"c\"\n+ row (3)"
+    /// );
+    /// ```
+    pub fn diff(
+        &self,
+        target: &VirtualData,
+        key_columns: &[impl AsRef<str>],
+    ) -> DcsvResult<DataDiff> {
+        diff(self, &self.columns, target, &target.columns, key_columns)
+    }
+}
+
+impl VirtualArray {
+    /// Compare array with a target array by key columns
+    ///
+    /// Key columns can be either column names or column indices of source and should exist in
+    /// both arrays. Name is matched first, thus a numeric header is not treated as an index.
+    /// Duplicate column name refers to a first column with the name. Duplicate key in either
+    /// array is an error.
+    pub fn diff(
+        &self,
+        target: &VirtualArray,
+        key_columns: &[impl AsRef<str>],
+    ) -> DcsvResult<DataDiff> {
+        diff(self, &self.columns, target, &target.columns, key_columns)
+    }
+}
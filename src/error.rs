@@ -15,6 +15,10 @@ pub enum DcsvError {
     InvalidColumn(String),
     InvalidCellData(String),
     CommandError(String),
+    ConstraintViolation(String),
+    InvalidSchema(String),
+    InvalidQuery(String),
+    InvalidExpression(String),
 }
 
 impl std::fmt::Display for DcsvError {
@@ -29,6 +33,10 @@ impl std::fmt::Display for DcsvError {
             Self::InvalidColumn(txt) => write!(f, "ERR : Invalid column =\n{0}", txt),
             Self::InvalidCellData(txt) => write!(f, "ERR : Invalid cell data =\n{0}", txt),
             Self::CommandError(txt) => write!(f, "ERR : Invalid command call =\n{0}", txt),
+            Self::ConstraintViolation(txt) => write!(f, "ERR : Constraint violation =\n{0}", txt),
+            Self::InvalidSchema(txt) => write!(f, "ERR : Invalid schema =\n{0}", txt),
+            Self::InvalidQuery(txt) => write!(f, "ERR : Invalid query =\n{0}", txt),
+            Self::InvalidExpression(txt) => write!(f, "ERR : Invalid expression =\n{0}", txt),
         }
     }
 }
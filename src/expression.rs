@@ -0,0 +1,691 @@
+//! Value expression
+//!
+//! Expression is a small arithmetic language which computes a value from references.
+//!
+//! ```text
+//! price * qty - discount
+//! first || ' ' || last
+//! SUM(B2:B10) / COUNT(B2:B10)
+//! ```
+//!
+//! - Reference is an identifier or a name quoted with backticks, e.g. `` `unit price` ``
+//! - Literal is a number or a text quoted with single or double quotes
+//! - Arithmetic operators are +, -, *, / and % over numbers
+//! - || concatenates text forms of values and binds looser than arithmetic
+//! - Function is called with parentheses and its name is case insensitive
+//! - Range of references is written as "from:to" and only allowed as a function argument
+//!
+//! Arithmetic works on integers, thus division truncates. Empty value, including a text of only
+//! whitespaces, is treated as zero in arithmetic and text which is not a number is an error.
+//!
+//! Supported functions are SUM, MIN, MAX, COUNT, AVERAGE, CONCAT, ABS, LEN, UPPER and LOWER.
+//! Aggregating functions ignore empty values.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::Value;
+use std::collections::HashSet;
+
+/// Source of referenced values
+pub(crate) trait Resolver {
+    /// Get a value of a reference
+    fn value(&self, reference: &str) -> DcsvResult<Value>;
+    /// Get values of a range of references
+    fn range(&self, from: &str, to: &str) -> DcsvResult<Vec<Value>>;
+}
+
+/// Parsed value expression
+///
+/// Expression references columns by names and literals are numbers or quoted texts. Arithmetic
+/// operators work on integers and || concatenates text forms of values.
+///
+/// Empty value, including a text of only whitespaces, is treated as zero in arithmetic. Thus an
+/// empty discount of `price - discount` subtracts nothing. Text which is not a number is an error
+/// in arithmetic.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    expr: Expr,
+}
+
+impl Expression {
+    /// Parse an expression from source text
+    pub fn parse(src: &str) -> DcsvResult<Self> {
+        let tokens = tokenize(src)?;
+        let mut parser = ExpressionParser { tokens, cursor: 0 };
+        let expr = parser.parse_concat()?;
+        if let Some(token) = parser.peek() {
+            return Err(DcsvError::InvalidExpression(format!(
+                "Unexpected token \"{}\"",
+                token
+            )));
+        }
+        expr.check_ranges(false)?;
+        Ok(Self { expr })
+    }
+
+    /// Get references which expression refers to
+    ///
+    /// Ends of ranges are not included.
+    pub fn get_references(&self) -> Vec<&str> {
+        let mut references = vec![];
+        self.expr.collect_references(&mut references);
+        let mut seen = HashSet::new();
+        references.retain(|c| seen.insert(*c));
+        references
+    }
+
+    /// Get ranges which expression refers to
+    pub fn get_ranges(&self) -> Vec<(&str, &str)> {
+        let mut ranges = vec![];
+        self.expr.collect_ranges(&mut ranges);
+        ranges
+    }
+
+    /// Rename references
+    pub(crate) fn rename_reference(&mut self, name: &str, new_name: &str) {
+        self.map_references(|reference| {
+            if reference == name {
+                new_name.to_string()
+            } else {
+                reference.to_string()
+            }
+        });
+    }
+
+    /// Replace every reference including ends of ranges
+    pub(crate) fn map_references(&mut self, map: impl Fn(&str) -> String) {
+        self.expr.map_references(&map);
+    }
+
+    /// Evaluate expression with a resolver
+    pub(crate) fn eval(&self, resolver: &impl Resolver) -> DcsvResult<Value> {
+        self.expr.eval(resolver)
+    }
+}
+
+impl std::str::FromStr for Expression {
+    type Err = DcsvError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Concat,
+}
+
+impl Operator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Concat => 1,
+            Self::Add | Self::Sub => 2,
+            Self::Mul | Self::Div | Self::Rem => 3,
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::Concat => "||",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Function {
+    Sum,
+    Min,
+    Max,
+    Count,
+    Average,
+    Concat,
+    Abs,
+    Len,
+    Upper,
+    Lower,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        let function = match name.to_uppercase().as_str() {
+            "SUM" => Self::Sum,
+            "MIN" => Self::Min,
+            "MAX" => Self::Max,
+            "COUNT" => Self::Count,
+            "AVERAGE" => Self::Average,
+            "CONCAT" => Self::Concat,
+            "ABS" => Self::Abs,
+            "LEN" => Self::Len,
+            "UPPER" => Self::Upper,
+            "LOWER" => Self::Lower,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Self::Sum => "SUM",
+            Self::Min => "MIN",
+            Self::Max => "MAX",
+            Self::Count => "COUNT",
+            Self::Average => "AVERAGE",
+            Self::Concat => "CONCAT",
+            Self::Abs => "ABS",
+            Self::Len => "LEN",
+            Self::Upper => "UPPER",
+            Self::Lower => "LOWER",
+        }
+    }
+
+    /// Check if function takes a single scalar argument
+    fn is_scalar(&self) -> bool {
+        matches!(self, Self::Abs | Self::Len | Self::Upper | Self::Lower)
+    }
+
+    fn apply(&self, values: Vec<Value>) -> DcsvResult<Value> {
+        let mut present = values.into_iter().filter(|v| !v.is_empty());
+        let value = match self {
+            Self::Sum => Value::Number(
+                present.try_fold(0isize, |sum, v| checked(sum.checked_add(to_number(&v)?)))?,
+            ),
+            Self::Min | Self::Max => {
+                let numbers = present
+                    .map(|v| to_number(&v))
+                    .collect::<DcsvResult<Vec<_>>>()?;
+                let number = if *self == Self::Min {
+                    numbers.into_iter().min()
+                } else {
+                    numbers.into_iter().max()
+                };
+                number.map(Value::Number).unwrap_or_default()
+            }
+            Self::Count => Value::Number(present.count() as isize),
+            Self::Average => {
+                let numbers = present
+                    .map(|v| to_number(&v))
+                    .collect::<DcsvResult<Vec<_>>>()?;
+                if numbers.is_empty() {
+                    Value::default()
+                } else {
+                    let sum = numbers.iter().map(|n| *n as f64).sum::<f64>();
+                    Value::Text((sum / numbers.len() as f64).to_string())
+                }
+            }
+            Self::Concat => Value::Text(present.map(|v| v.to_string()).collect()),
+            Self::Abs => match present.next() {
+                Some(value) => Value::Number(checked(to_number(&value)?.checked_abs())?),
+                None => Value::default(),
+            },
+            Self::Len => Value::Number(
+                present
+                    .next()
+                    .map(|v| v.to_string().chars().count())
+                    .unwrap_or(0) as isize,
+            ),
+            Self::Upper => Value::Text(
+                present
+                    .next()
+                    .map(|v| v.to_string().to_uppercase())
+                    .unwrap_or_default(),
+            ),
+            Self::Lower => Value::Text(
+                present
+                    .next()
+                    .map(|v| v.to_string().to_lowercase())
+                    .unwrap_or_default(),
+            ),
+        };
+        Ok(value)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Number(isize),
+    Text(String),
+    Reference(String),
+    Range(String, String),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn collect_references<'a>(&'a self, references: &mut Vec<&'a str>) {
+        match self {
+            Self::Number(_) | Self::Text(_) | Self::Range(..) => (),
+            Self::Reference(name) => references.push(name),
+            Self::Negate(expr) => expr.collect_references(references),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.collect_references(references);
+                rhs.collect_references(references);
+            }
+            Self::Call(_, args) => args.iter().for_each(|a| a.collect_references(references)),
+        }
+    }
+
+    fn collect_ranges<'a>(&'a self, ranges: &mut Vec<(&'a str, &'a str)>) {
+        match self {
+            Self::Number(_) | Self::Text(_) | Self::Reference(_) => (),
+            Self::Range(from, to) => ranges.push((from, to)),
+            Self::Negate(expr) => expr.collect_ranges(ranges),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.collect_ranges(ranges);
+                rhs.collect_ranges(ranges);
+            }
+            Self::Call(_, args) => args.iter().for_each(|a| a.collect_ranges(ranges)),
+        }
+    }
+
+    /// Check if ranges are only used as function arguments
+    fn check_ranges(&self, allowed: bool) -> DcsvResult<()> {
+        match self {
+            Self::Number(_) | Self::Text(_) | Self::Reference(_) => Ok(()),
+            Self::Range(..) if allowed => Ok(()),
+            Self::Range(..) => Err(DcsvError::InvalidExpression(
+                "Range is only allowed as a function argument".to_string(),
+            )),
+            Self::Negate(expr) => expr.check_ranges(false),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.check_ranges(false)?;
+                rhs.check_ranges(false)
+            }
+            Self::Call(_, args) => args.iter().try_for_each(|a| a.check_ranges(true)),
+        }
+    }
+
+    fn map_references(&mut self, map: &impl Fn(&str) -> String) {
+        match self {
+            Self::Number(_) | Self::Text(_) => (),
+            Self::Reference(reference) => *reference = map(reference),
+            Self::Range(from, to) => {
+                *from = map(from);
+                *to = map(to);
+            }
+            Self::Negate(expr) => expr.map_references(map),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.map_references(map);
+                rhs.map_references(map);
+            }
+            Self::Call(_, args) => args.iter_mut().for_each(|a| a.map_references(map)),
+        }
+    }
+
+    fn eval(&self, resolver: &impl Resolver) -> DcsvResult<Value> {
+        let value = match self {
+            Self::Number(num) => Value::Number(*num),
+            Self::Text(text) => Value::Text(text.clone()),
+            Self::Reference(name) => resolver.value(name)?,
+            Self::Range(..) => {
+                return Err(DcsvError::InvalidExpression(
+                    "Range is only allowed as a function argument".to_string(),
+                ))
+            }
+            Self::Negate(expr) => {
+                Value::Number(checked(to_number(&expr.eval(resolver)?)?.checked_neg())?)
+            }
+            Self::Binary(Operator::Concat, lhs, rhs) => {
+                Value::Text(format!("{}{}", lhs.eval(resolver)?, rhs.eval(resolver)?))
+            }
+            Self::Binary(operator, lhs, rhs) => {
+                let lhs = to_number(&lhs.eval(resolver)?)?;
+                let rhs = to_number(&rhs.eval(resolver)?)?;
+                if rhs == 0 && matches!(operator, Operator::Div | Operator::Rem) {
+                    return Err(DcsvError::InvalidCellData("Division by zero".to_string()));
+                }
+                Value::Number(checked(match operator {
+                    Operator::Add => lhs.checked_add(rhs),
+                    Operator::Sub => lhs.checked_sub(rhs),
+                    Operator::Mul => lhs.checked_mul(rhs),
+                    Operator::Div => lhs.checked_div(rhs),
+                    Operator::Rem => lhs.checked_rem(rhs),
+                    Operator::Concat => unreachable!("Concat is evaluated as text"),
+                })?)
+            }
+            Self::Call(function, args) => {
+                let mut values = vec![];
+                for arg in args {
+                    match arg {
+                        Self::Range(from, to) => values.extend(resolver.range(from, to)?),
+                        arg => values.push(arg.eval(resolver)?),
+                    }
+                }
+                function.apply(values)?
+            }
+        };
+        Ok(value)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Binary(operator, ..) => operator.precedence(),
+            _ => u8::MAX,
+        }
+    }
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(num) => write!(f, "{}", num),
+            Self::Text(text) => write!(f, "'{}'", text.replace('\\', "\\\\").replace('\'', "\\'")),
+            Self::Reference(name) => write_reference(f, name),
+            Self::Range(from, to) => {
+                write_reference(f, from)?;
+                write!(f, ":")?;
+                write_reference(f, to)
+            }
+            Self::Negate(expr) => {
+                if expr.precedence() == u8::MAX {
+                    write!(f, "-{}", expr)
+                } else {
+                    write!(f, "-({})", expr)
+                }
+            }
+            Self::Binary(operator, lhs, rhs) => {
+                // Operators are left associative, thus right operand with a same precedence
+                // needs parentheses
+                if lhs.precedence() < operator.precedence() {
+                    write!(f, "({})", lhs)?;
+                } else {
+                    write!(f, "{}", lhs)?;
+                }
+                write!(f, " {} ", operator.symbol())?;
+                if rhs.precedence() <= operator.precedence() {
+                    write!(f, "({})", rhs)
+                } else {
+                    write!(f, "{}", rhs)
+                }
+            }
+            Self::Call(function, args) => {
+                write!(f, "{}(", function.name())?;
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Write a reference with backticks when it is not an identifier
+fn write_reference(f: &mut std::fmt::Formatter<'_>, name: &str) -> std::fmt::Result {
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .map(|c| c.is_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_alphanumeric() || c == '_');
+    if is_identifier {
+        write!(f, "{}", name)
+    } else {
+        write!(f, "`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+    }
+}
+
+/// Convert a value into a number
+///
+/// Empty value is zero.
+fn to_number(value: &Value) -> DcsvResult<isize> {
+    match value {
+        Value::Number(num) => Ok(*num),
+        Value::Text(text) if text.trim().is_empty() => Ok(0),
+        Value::Text(text) => text
+            .trim()
+            .parse()
+            .map_err(|_| DcsvError::InvalidCellData(format!("\"{}\" is not a number", text))),
+    }
+}
+
+/// Convert an overflown result into an error
+fn checked(result: Option<isize>) -> DcsvResult<isize> {
+    result.ok_or_else(|| DcsvError::InvalidCellData("Arithmetic overflow".to_string()))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Reference(String),
+    Number(isize),
+    Text(String),
+    Operator(Operator),
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reference(name) => write!(f, "{}", name),
+            Self::Number(num) => write!(f, "{}", num),
+            Self::Text(text) => write!(f, "'{}'", text),
+            Self::Operator(operator) => write!(f, "{}", operator.symbol()),
+            Self::LParen => write!(f, "("),
+            Self::RParen => write!(f, ")"),
+            Self::Comma => write!(f, ","),
+            Self::Colon => write!(f, ":"),
+        }
+    }
+}
+
+/// Split expression source into tokens
+fn tokenize(src: &str) -> DcsvResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = src.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ' ' | '\t' | '\r' | '\n' => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Sub),
+            '*' => Token::Operator(Operator::Mul),
+            '/' => Token::Operator(Operator::Div),
+            '%' => Token::Operator(Operator::Rem),
+            '|' => {
+                if chars.next_if_eq(&'|').is_none() {
+                    return Err(DcsvError::InvalidExpression(
+                        "Expected \"||\" but got single \"|\"".to_string(),
+                    ));
+                }
+                Token::Operator(Operator::Concat)
+            }
+            '\'' | '"' => Token::Text(take_quoted(&mut chars, ch)?),
+            '`' => Token::Reference(take_quoted(&mut chars, '`')?),
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                    number.push(c);
+                }
+                Token::Number(number.parse().map_err(|_| {
+                    DcsvError::InvalidExpression(format!("Invalid number \"{}\"", number))
+                })?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                Token::Reference(ident)
+            }
+            c => {
+                return Err(DcsvError::InvalidExpression(format!(
+                    "Unexpected character \"{}\"",
+                    c
+                )))
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Take characters until a closing quote
+///
+/// Backslash escapes a quote character and a backslash.
+fn take_quoted(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    quote: char,
+) -> DcsvResult<String> {
+    let mut text = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(next) if next == quote || next == '\\' => text.push(next),
+                Some(next) => {
+                    text.push(ch);
+                    text.push(next);
+                }
+                None => break,
+            },
+            c if c == quote => return Ok(text),
+            c => text.push(c),
+        }
+    }
+    Err(DcsvError::InvalidExpression(format!(
+        "Missing closing quote {}",
+        quote
+    )))
+}
+
+/// Recursive descent parser of expression tokens
+struct ExpressionParser {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl ExpressionParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.cursor)
+    }
+
+    fn next(&mut self) -> DcsvResult<Token> {
+        let token = self.tokens.get(self.cursor).cloned().ok_or_else(|| {
+            DcsvError::InvalidExpression("Unexpected end of expression".to_string())
+        })?;
+        self.cursor += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> DcsvResult<()> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(DcsvError::InvalidExpression(format!(
+                "Expected \"{}\" but got \"{}\"",
+                expected, token
+            )));
+        }
+        Ok(())
+    }
+
+    /// Parse left associative binary operators of a same precedence
+    fn parse_binary(
+        &mut self,
+        operators: &[Operator],
+        operand: fn(&mut Self) -> DcsvResult<Expr>,
+    ) -> DcsvResult<Expr> {
+        let mut expr = operand(self)?;
+        while let Some(Token::Operator(operator)) = self.peek() {
+            if !operators.contains(operator) {
+                break;
+            }
+            let operator = *operator;
+            self.cursor += 1;
+            expr = Expr::Binary(operator, Box::new(expr), Box::new(operand(self)?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_concat(&mut self) -> DcsvResult<Expr> {
+        self.parse_binary(&[Operator::Concat], Self::parse_additive)
+    }
+
+    fn parse_additive(&mut self) -> DcsvResult<Expr> {
+        self.parse_binary(&[Operator::Add, Operator::Sub], Self::parse_multiplicative)
+    }
+
+    fn parse_multiplicative(&mut self) -> DcsvResult<Expr> {
+        self.parse_binary(
+            &[Operator::Mul, Operator::Div, Operator::Rem],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> DcsvResult<Expr> {
+        match self.next()? {
+            Token::Operator(Operator::Sub) => match self.parse_unary()? {
+                Expr::Number(num) => Ok(Expr::Number(-num)),
+                expr => Ok(Expr::Negate(Box::new(expr))),
+            },
+            Token::Operator(Operator::Add) => self.parse_unary(),
+            Token::LParen => {
+                let expr = self.parse_concat()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Number(num) => Ok(Expr::Number(num)),
+            Token::Text(text) => Ok(Expr::Text(text)),
+            Token::Reference(name) => match self.peek() {
+                Some(Token::LParen) => self.parse_call(name),
+                Some(Token::Colon) => {
+                    self.cursor += 1;
+                    match self.next()? {
+                        Token::Reference(to) => Ok(Expr::Range(name, to)),
+                        token => Err(DcsvError::InvalidExpression(format!(
+                            "Expected an end of range but got \"{}\"",
+                            token
+                        ))),
+                    }
+                }
+                _ => Ok(Expr::Reference(name)),
+            },
+            token => Err(DcsvError::InvalidExpression(format!(
+                "Expected an operand but got \"{}\"",
+                token
+            ))),
+        }
+    }
+
+    fn parse_call(&mut self, name: String) -> DcsvResult<Expr> {
+        let function = Function::from_name(&name).ok_or_else(|| {
+            DcsvError::InvalidExpression(format!("Unknown function \"{}\"", name))
+        })?;
+        self.expect(Token::LParen)?;
+        let mut args = vec![self.parse_concat()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.cursor += 1;
+            args.push(self.parse_concat()?);
+        }
+        self.expect(Token::RParen)?;
+        if function.is_scalar() && (args.len() != 1 || matches!(args[0], Expr::Range(..))) {
+            return Err(DcsvError::InvalidExpression(format!(
+                "{} takes a single value",
+                function.name()
+            )));
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
@@ -0,0 +1,435 @@
+//! Spreadsheet formulas
+//!
+//! Formula is an expression whose references are cells of a virtual array in A1 notation, e.g.
+//! `=SUM(B1:B10) * 2`. Column is written in letters and row is a 1 based row number, thus A1 is
+//! a cell of row 0 and column 0. Header is not a part of cells.
+//!
+//! Formula cells are recomputed in a dependency order when referenced cells change through
+//! VCont methods. Circular references are rejected when a formula is set. Failed computation
+//! sets "#REF!" for invalid references and "#VALUE!" for other errors.
+//!
+//! Inserting, deleting, moving and sorting rows or columns moves formulas with their cells and
+//! shifts references. Reference to a deleted cell becomes "#REF!".
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::expression::{Expression, Resolver};
+use crate::utils::ALPHABET;
+use crate::value::Value;
+use crate::vcont::VCont;
+use crate::virtual_array::VirtualArray;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Value of a formula which refers to an invalid cell
+pub const FORMULA_REF_ERROR: &str = "#REF!";
+/// Value of a formula which failed to compute
+pub const FORMULA_VALUE_ERROR: &str = "#VALUE!";
+
+/// Coordinate of a cell as row and column
+type Cell = (usize, usize);
+
+/// Formulas of cells with reverse dependencies
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Formulas {
+    cells: HashMap<Cell, Expression>,
+    dependents: HashMap<Cell, HashSet<Cell>>,
+}
+
+impl Formulas {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.cells.clear();
+        self.dependents.clear();
+    }
+
+    fn insert(&mut self, cell: Cell, expression: Expression) {
+        for dependency in dependencies(&expression) {
+            self.dependents.entry(dependency).or_default().insert(cell);
+        }
+        self.cells.insert(cell, expression);
+    }
+
+    pub(crate) fn remove(&mut self, cell: &Cell) -> Option<Expression> {
+        let expression = self.cells.remove(cell)?;
+        for dependency in dependencies(&expression) {
+            if let Some(dependents) = self.dependents.get_mut(&dependency) {
+                dependents.remove(cell);
+                if dependents.is_empty() {
+                    self.dependents.remove(&dependency);
+                }
+            }
+        }
+        Some(expression)
+    }
+
+    /// Move formulas and their references with mapping functions
+    ///
+    /// Formula of a cell which is mapped to none is removed and reference to such cell becomes
+    /// an invalid reference.
+    pub(crate) fn remap(
+        &mut self,
+        map_row: impl Fn(usize) -> Option<usize>,
+        map_column: impl Fn(usize) -> Option<usize>,
+    ) {
+        let map = |(row, column): Cell| Some((map_row(row)?, map_column(column)?));
+        let cells = std::mem::take(&mut self.cells);
+        self.dependents.clear();
+        for (cell, mut expression) in cells {
+            let Some(cell) = map(cell) else {
+                continue;
+            };
+            expression.map_references(|reference| match parse_a1(reference).and_then(map) {
+                Some((row, column)) => to_a1(row, column),
+                None if parse_a1(reference).is_some() => FORMULA_REF_ERROR.to_string(),
+                None => reference.to_string(),
+            });
+            self.insert(cell, expression);
+        }
+    }
+
+    /// Check if a formula of a cell would make a circular reference
+    fn is_circular(&self, cell: Cell, expression: &Expression) -> bool {
+        let mut stack = dependencies(expression).into_iter().collect::<Vec<_>>();
+        let mut visited = HashSet::new();
+        while let Some(current) = stack.pop() {
+            if current == cell {
+                return true;
+            }
+            if !visited.insert(current) {
+                continue;
+            }
+            if let Some(expression) = self.cells.get(&current) {
+                stack.extend(dependencies(expression));
+            }
+        }
+        false
+    }
+
+    /// Get formula cells which depend on changed cells directly or indirectly
+    fn affected_by(&self, changed: &[Cell]) -> HashSet<Cell> {
+        let mut affected = HashSet::new();
+        let mut queue = changed.iter().copied().collect::<VecDeque<_>>();
+        while let Some(cell) = queue.pop_front() {
+            for dependent in self.dependents.get(&cell).into_iter().flatten() {
+                if affected.insert(*dependent) {
+                    queue.push_back(*dependent);
+                }
+            }
+        }
+        affected
+    }
+
+    /// Sort formula cells so that every cell comes after its dependencies
+    fn order(&self, cells: HashSet<Cell>) -> Vec<Cell> {
+        let mut pending = cells
+            .iter()
+            .map(|cell| {
+                let count = dependencies(&self.cells[cell])
+                    .iter()
+                    .filter(|d| cells.contains(d))
+                    .count();
+                (*cell, count)
+            })
+            .collect::<HashMap<_, _>>();
+        let mut ready = pending
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(cell, _)| *cell)
+            .collect::<Vec<_>>();
+        ready.sort_unstable();
+        let mut queue = ready.into_iter().collect::<VecDeque<_>>();
+        let mut ordered = vec![];
+        while let Some(cell) = queue.pop_front() {
+            ordered.push(cell);
+            for dependent in self.dependents.get(&cell).into_iter().flatten() {
+                if let Some(count) = pending.get_mut(dependent) {
+                    *count -= 1;
+                    if *count == 0 {
+                        queue.push_back(*dependent);
+                    }
+                }
+            }
+        }
+        ordered
+    }
+}
+
+/// Get cells which an expression refers to
+fn dependencies(expression: &Expression) -> HashSet<Cell> {
+    let mut cells = expression
+        .get_references()
+        .into_iter()
+        .filter_map(parse_a1)
+        .collect::<HashSet<_>>();
+    for (from, to) in expression.get_ranges() {
+        if let (Some(from), Some(to)) = (parse_a1(from), parse_a1(to)) {
+            for row in from.0.min(to.0)..=from.0.max(to.0) {
+                for column in from.1.min(to.1)..=from.1.max(to.1) {
+                    cells.insert((row, column));
+                }
+            }
+        }
+    }
+    cells
+}
+
+/// Parse a reference in A1 notation into a cell
+fn parse_a1(reference: &str) -> Option<Cell> {
+    let digits = reference.find(|c: char| c.is_ascii_digit())?;
+    let (letters, number) = reference.split_at(digits);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let row = number.parse::<usize>().ok()?.checked_sub(1)?;
+    let mut column = 0usize;
+    for letter in letters.to_ascii_uppercase().bytes() {
+        column = column
+            .checked_mul(ALPHABET.len())?
+            .checked_add((letter - b'A') as usize + 1)?;
+    }
+    Some((row, column - 1))
+}
+
+/// Convert a cell into A1 notation
+fn to_a1(row: usize, column: usize) -> String {
+    let mut letters = vec![];
+    let mut column = column + 1;
+    while column > 0 {
+        let rem = (column - 1) % ALPHABET.len();
+        letters.push(ALPHABET[rem].to_uppercase());
+        column = (column - 1) / ALPHABET.len();
+    }
+    letters.reverse();
+    format!("{}{}", letters.concat(), row + 1)
+}
+
+/// Resolve references as cells of a virtual array
+struct ArrayResolver<'a> {
+    array: &'a VirtualArray,
+}
+
+impl ArrayResolver<'_> {
+    /// Get a value of a cell
+    ///
+    /// Invalid reference of a formula cell propagates as an invalid reference.
+    fn cell(&self, cell: Cell) -> DcsvResult<Value> {
+        let value = self
+            .array
+            .get_cell(cell.0, cell.1)
+            .ok_or(DcsvError::OutOfRangeError)?;
+        if self.array.formulas.cells.contains_key(&cell) && value.to_string() == FORMULA_REF_ERROR {
+            return Err(DcsvError::OutOfRangeError);
+        }
+        Ok(value.clone())
+    }
+}
+
+impl Resolver for ArrayResolver<'_> {
+    fn value(&self, reference: &str) -> DcsvResult<Value> {
+        self.cell(parse_a1(reference).ok_or(DcsvError::OutOfRangeError)?)
+    }
+
+    fn range(&self, from: &str, to: &str) -> DcsvResult<Vec<Value>> {
+        let from = parse_a1(from).ok_or(DcsvError::OutOfRangeError)?;
+        let to = parse_a1(to).ok_or(DcsvError::OutOfRangeError)?;
+        let mut values = vec![];
+        for row in from.0.min(to.0)..=from.0.max(to.0) {
+            for column in from.1.min(to.1)..=from.1.max(to.1) {
+                values.push(self.cell((row, column))?);
+            }
+        }
+        Ok(values)
+    }
+}
+
+impl VirtualArray {
+    /// Set a formula to a cell and compute it
+    ///
+    /// Leading "=" is optional. References should be in A1 notation and can point to cells out
+    /// of range, which compute to "#REF!". This fails when formula makes a circular reference.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use dcsv::{Reader, VCont, Value};
+    ///
+    /// let mut array = Reader::new()
+    ///     .array_from_stream("a,b\n1,2\n3,4".as_bytes())
+    ///     .expect("Failed to read array");
+    /// array.set_formula(1, 1, "=SUM(A1:B1) * A2").expect("Failed to set formula");
+    /// assert_eq!(array.get_cell(1, 1), Some(&Value::Number(9)));
+    ///
+    /// array.set_cell(0, 0, Value::Text("2".to_string())).expect("Failed to set cell");
+    /// assert_eq!(array.get_cell(1, 1), Some(&Value::Number(12)));
+    /// ```
+    pub fn set_formula(&mut self, x: usize, y: usize, formula: &str) -> DcsvResult<()> {
+        if self.get_cell(x, y).is_none() {
+            return Err(DcsvError::OutOfRangeError);
+        }
+        let formula = formula.trim();
+        let mut expression = Expression::parse(formula.strip_prefix('=').unwrap_or(formula))?;
+        let ranges = expression.get_ranges();
+        let ends = ranges.iter().flat_map(|(from, to)| [*from, *to]);
+        if let Some(reference) = expression
+            .get_references()
+            .into_iter()
+            .chain(ends)
+            .find(|r| parse_a1(r).is_none())
+        {
+            return Err(DcsvError::InvalidExpression(format!(
+                "\"{}\" is not a cell reference",
+                reference
+            )));
+        }
+        // Normalize references into upper case
+        expression.map_references(|reference| match parse_a1(reference) {
+            Some((row, column)) => to_a1(row, column),
+            None => reference.to_string(),
+        });
+        if self.formulas.is_circular((x, y), &expression) {
+            return Err(DcsvError::InvalidExpression(format!(
+                "Formula of {} makes a circular reference",
+                to_a1(x, y)
+            )));
+        }
+
+        self.formulas.remove(&(x, y));
+        let value = self.evaluate(&expression);
+        self.formulas.insert((x, y), expression);
+        self.set_computed_value((x, y), value);
+        self.recalculate_from(&[(x, y)]);
+        Ok(())
+    }
+
+    /// Get a formula of a cell with a leading "="
+    pub fn get_formula(&self, x: usize, y: usize) -> Option<String> {
+        self.formulas
+            .cells
+            .get(&(x, y))
+            .map(|expression| format!("={}", expression))
+    }
+
+    /// Remove a formula of a cell
+    ///
+    /// Computed value is kept as it is. This returns false when cell has no formula.
+    pub fn remove_formula(&mut self, x: usize, y: usize) -> bool {
+        self.formulas.remove(&(x, y)).is_some()
+    }
+
+    /// Get coordinates of cells which have formulas
+    pub fn get_formula_cells(&self) -> Vec<(usize, usize)> {
+        let mut cells = self.formulas.cells.keys().copied().collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Recompute every formula
+    ///
+    /// This is useful after rows are modified directly through public fields.
+    pub fn recalculate(&mut self) {
+        let cells = self.formulas.cells.keys().copied().collect();
+        for cell in self.formulas.order(cells) {
+            self.recompute_cell(cell);
+        }
+    }
+
+    /// Recompute formulas which depend on changed cells
+    pub(crate) fn recalculate_from(&mut self, changed: &[(usize, usize)]) {
+        if self.formulas.is_empty() {
+            return;
+        }
+        let affected = self.formulas.affected_by(changed);
+        for cell in self.formulas.order(affected) {
+            self.recompute_cell(cell);
+        }
+    }
+
+    /// Move formulas with mapping functions of rows and columns and recompute them
+    pub(crate) fn remap_formulas(
+        &mut self,
+        map_row: impl Fn(usize) -> Option<usize>,
+        map_column: impl Fn(usize) -> Option<usize>,
+    ) {
+        if self.formulas.is_empty() {
+            return;
+        }
+        self.formulas.remap(map_row, map_column);
+        self.recalculate();
+    }
+
+    /// Recompute formulas of given cells and formulas which depend on them
+    pub(crate) fn recompute_cells(&mut self, cells: &[(usize, usize)]) {
+        if self.formulas.is_empty() {
+            return;
+        }
+        let mut affected = self.formulas.affected_by(cells);
+        affected.extend(
+            cells
+                .iter()
+                .filter(|cell| self.formulas.cells.contains_key(cell)),
+        );
+        for cell in self.formulas.order(affected) {
+            self.recompute_cell(cell);
+        }
+    }
+
+    /// Get formulas which are removed or broken when cells are overwritten or deleted
+    ///
+    /// Filter takes a row and a column of a cell. Formulas which refer to given cells are
+    /// included only when cells are deleted.
+    pub(crate) fn get_affected_formulas(
+        &self,
+        cells: impl Fn(usize, usize) -> bool,
+        deleted: bool,
+    ) -> Vec<(usize, usize, Expression)> {
+        self.formulas
+            .cells
+            .iter()
+            .filter(|((row, column), expression)| {
+                cells(*row, *column)
+                    || (deleted && dependencies(expression).iter().any(|(r, c)| cells(*r, *c)))
+            })
+            .map(|((row, column), expression)| (*row, *column, expression.clone()))
+            .collect()
+    }
+
+    /// Set formulas which were taken by get_affected_formulas and recompute them
+    pub(crate) fn restore_formulas(&mut self, formulas: &[(usize, usize, Expression)]) {
+        for (row, column, expression) in formulas {
+            self.formulas.remove(&(*row, *column));
+            self.formulas.insert((*row, *column), expression.clone());
+        }
+        let cells = formulas
+            .iter()
+            .map(|(row, column, _)| (*row, *column))
+            .collect::<Vec<_>>();
+        self.recompute_cells(&cells);
+    }
+
+    fn recompute_cell(&mut self, cell: Cell) {
+        let value = self.evaluate(&self.formulas.cells[&cell]);
+        self.set_computed_value(cell, value);
+    }
+
+    /// Compute a value of an expression
+    fn evaluate(&self, expression: &Expression) -> Value {
+        match expression.eval(&ArrayResolver { array: self }) {
+            Ok(value) => value,
+            Err(DcsvError::OutOfRangeError) => Value::Text(FORMULA_REF_ERROR.to_string()),
+            Err(_) => Value::Text(FORMULA_VALUE_ERROR.to_string()),
+        }
+    }
+
+    fn set_computed_value(&mut self, (row, column): Cell, value: Value) {
+        if let Some(cell) = self.rows.get_mut(row).and_then(|r| r.get_mut(column)) {
+            self.metas[column].update_width_from_value(&value);
+            self.changes.set_cell(row, column, cell, &value);
+            *cell = value;
+        }
+    }
+}
@@ -0,0 +1,786 @@
+//! Undo and redo history
+//!
+//! History wraps a container and records every mutating VCont call as a reversible operation.
+//! Operations keep only what their edits lose, e.g. values of a deleted column, a permutation of
+//! sorted rows or previous values of changed cells. Formulas of an array which an edit removes
+//! or breaks are kept as a separate operation. Undo and redo apply operations in place and a
+//! failed step is rolled back by reverting its already applied operations.
+//!
+//! Only record keeps a copy of a whole container because an arbitrary edit has no inverse.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::expression::Expression;
+use crate::sort::{inverse, row_order, SortKey};
+use crate::value::Value;
+use crate::vcont::{CellAlignType, VCont};
+use crate::virtual_array::{RemovedArrayColumn, VirtualArray};
+use crate::virtual_data::{RemovedColumn, VirtualData};
+use std::collections::VecDeque;
+
+/// Default count of undoable steps
+pub const HISTORY_DEPTH: usize = 100;
+
+/// Container which can be recorded by a history
+///
+/// Methods other than get_column_name are building blocks of recorded operations. They mostly
+/// restore states which existed before, thus they don't check row validators nor foreign keys.
+pub trait Recordable: VCont + Clone {
+    /// Column which is removed with its values and everything attached to it
+    type RemovedColumn: Clone;
+
+    /// Get a name of a column
+    fn get_column_name(&self, column_index: usize) -> Option<&str>;
+
+    /// Delete a column and get it back for restoration
+    fn take_column(&mut self, column_index: usize) -> DcsvResult<Self::RemovedColumn>;
+
+    /// Insert a column which was taken by take_column
+    ///
+    /// Column index should not be greater than a column count.
+    fn restore_column(&mut self, column_index: usize, column: Self::RemovedColumn);
+
+    /// Reorder rows with indices of original positions
+    ///
+    /// History sorts rows with this method, thus it should do what sort_rows does.
+    fn permute_rows(&mut self, indices: &[usize]);
+
+    /// Write values of cells as they were without removing formulas
+    ///
+    /// Container is not modified when this fails.
+    fn restore_cells(&mut self, cells: &[(usize, usize, Value)]) -> DcsvResult<()>;
+
+    /// Get formulas which are removed or broken when cells are overwritten or deleted
+    ///
+    /// Filter takes a row and a column of a cell. Formulas which refer to given cells are
+    /// included only when cells are deleted.
+    fn get_formulas(
+        &self,
+        _cells: impl Fn(usize, usize) -> bool,
+        _deleted: bool,
+    ) -> Vec<(usize, usize, Expression)> {
+        vec![]
+    }
+
+    /// Set formulas which were taken by get_formulas
+    fn restore_formulas(&mut self, _formulas: &[(usize, usize, Expression)]) {}
+}
+
+impl Recordable for VirtualData {
+    type RemovedColumn = RemovedColumn;
+
+    fn get_column_name(&self, column_index: usize) -> Option<&str> {
+        self.columns.get(column_index).map(|c| c.name.as_str())
+    }
+
+    fn take_column(&mut self, column_index: usize) -> DcsvResult<RemovedColumn> {
+        self.take_column(column_index)
+    }
+
+    fn restore_column(&mut self, column_index: usize, column: RemovedColumn) {
+        self.restore_column(column_index, column)
+    }
+
+    fn permute_rows(&mut self, indices: &[usize]) {
+        self.permute_rows(indices)
+    }
+
+    fn restore_cells(&mut self, cells: &[(usize, usize, Value)]) -> DcsvResult<()> {
+        self.restore_cells(cells)
+    }
+}
+
+impl Recordable for VirtualArray {
+    type RemovedColumn = RemovedArrayColumn;
+
+    fn get_column_name(&self, column_index: usize) -> Option<&str> {
+        self.columns.get(column_index).map(|c| c.name.as_str())
+    }
+
+    fn take_column(&mut self, column_index: usize) -> DcsvResult<RemovedArrayColumn> {
+        self.take_column(column_index)
+    }
+
+    fn restore_column(&mut self, column_index: usize, column: RemovedArrayColumn) {
+        self.restore_column(column_index, column)
+    }
+
+    fn permute_rows(&mut self, indices: &[usize]) {
+        self.permute_rows(indices)
+    }
+
+    fn restore_cells(&mut self, cells: &[(usize, usize, Value)]) -> DcsvResult<()> {
+        self.restore_cells(cells)
+    }
+
+    fn get_formulas(
+        &self,
+        cells: impl Fn(usize, usize) -> bool,
+        deleted: bool,
+    ) -> Vec<(usize, usize, Expression)> {
+        self.get_affected_formulas(cells, deleted)
+    }
+
+    fn restore_formulas(&mut self, formulas: &[(usize, usize, Expression)]) {
+        self.restore_formulas(formulas)
+    }
+}
+
+/// Reversible operation
+///
+/// Operations which own removed state give it back on undo and take it again on redo, thus
+/// undo and redo don't copy it.
+#[derive(Clone)]
+enum Operation<T: Recordable> {
+    SetCell {
+        x: usize,
+        y: usize,
+        before: Value,
+        after: Value,
+    },
+    SetRow {
+        row: usize,
+        before: Vec<Value>,
+        after: Vec<Value>,
+    },
+    SetColumn {
+        column: usize,
+        value: Value,
+        before: Vec<(usize, usize, Value)>,
+    },
+    SetCells {
+        before: Vec<(usize, usize, Value)>,
+        after: Vec<(usize, usize, Value)>,
+    },
+    InsertRow {
+        row: usize,
+        values: Vec<Value>,
+    },
+    DeleteRow {
+        row: usize,
+        values: Vec<Value>,
+    },
+    InsertColumn {
+        column: usize,
+        name: String,
+    },
+    DeleteColumn {
+        column: usize,
+        removed: Option<T::RemovedColumn>,
+    },
+    MoveRow {
+        from: usize,
+        to: usize,
+    },
+    MoveColumn {
+        from: usize,
+        to: usize,
+    },
+    RenameColumn {
+        column: usize,
+        before: String,
+        after: String,
+    },
+    SortRows {
+        indices: Vec<usize>,
+    },
+    /// Formulas which a next operation removes or breaks
+    Formulas {
+        formulas: Vec<(usize, usize, Expression)>,
+    },
+    /// Container which is swapped with a current one
+    Snapshot {
+        data: Box<T>,
+    },
+}
+
+impl<T: Recordable> Operation<T> {
+    fn undo(&mut self, data: &mut T) -> DcsvResult<()> {
+        match self {
+            Self::SetCell { x, y, before, .. } => data.set_cell(*x, *y, before.clone()),
+            Self::SetRow { row, before, .. } => data.set_row(*row, before),
+            Self::SetColumn { before, .. } => data.restore_cells(before),
+            Self::SetCells { before, .. } => data.restore_cells(before),
+            Self::InsertRow { row, .. } => {
+                data.delete_row(*row);
+                Ok(())
+            }
+            Self::DeleteRow { row, values } => data.insert_row(*row, Some(values)),
+            Self::InsertColumn { column, .. } => data.delete_column(*column),
+            Self::DeleteColumn { column, removed } => {
+                if *column > data.get_column_count() {
+                    return Err(DcsvError::OutOfRangeError);
+                }
+                if let Some(removed) = removed.take() {
+                    data.restore_column(*column, removed);
+                }
+                Ok(())
+            }
+            Self::MoveRow { from, to } => data.move_row(*to, *from),
+            Self::MoveColumn { from, to } => data.move_column(*to, *from),
+            Self::RenameColumn { column, before, .. } => data.rename_column(*column, before),
+            Self::SortRows { indices } => {
+                data.permute_rows(&inverse(indices));
+                Ok(())
+            }
+            Self::Formulas { formulas } => {
+                data.restore_formulas(formulas);
+                Ok(())
+            }
+            Self::Snapshot { data: snapshot } => {
+                std::mem::swap(data, snapshot.as_mut());
+                Ok(())
+            }
+        }
+    }
+
+    fn redo(&mut self, data: &mut T) -> DcsvResult<()> {
+        match self {
+            Self::SetCell { x, y, after, .. } => data.set_cell(*x, *y, after.clone()),
+            Self::SetRow { row, after, .. } => data.set_row(*row, after),
+            Self::SetColumn { column, value, .. } => data.set_column(*column, value.clone()),
+            Self::SetCells { after, .. } => data.restore_cells(after),
+            Self::InsertRow { row, values } => data.insert_row(*row, Some(values)),
+            Self::DeleteRow { row, .. } => {
+                data.delete_row(*row);
+                Ok(())
+            }
+            Self::InsertColumn { column, name } => data.insert_column(*column, name),
+            Self::DeleteColumn { column, removed } => {
+                *removed = Some(data.take_column(*column)?);
+                Ok(())
+            }
+            Self::MoveRow { from, to } => data.move_row(*from, *to),
+            Self::MoveColumn { from, to } => data.move_column(*from, *to),
+            Self::RenameColumn { column, after, .. } => data.rename_column(*column, after),
+            Self::SortRows { indices } => {
+                data.permute_rows(indices);
+                Ok(())
+            }
+            // Formulas are removed or broken again by a next operation
+            Self::Formulas { .. } => Ok(()),
+            Self::Snapshot { data: snapshot } => {
+                std::mem::swap(data, snapshot.as_mut());
+                Ok(())
+            }
+        }
+    }
+
+    fn run(&mut self, data: &mut T, undo: bool) -> DcsvResult<()> {
+        if undo {
+            self.undo(data)
+        } else {
+            self.redo(data)
+        }
+    }
+}
+
+/// Container with undo and redo stacks
+///
+/// Every mutating VCont call on history is recorded as a single step. Several edits between
+/// begin_group and end_group are recorded as a single step. Edits which fail are not recorded.
+///
+/// # Usage
+///
+/// ```rust
+/// use dcsv::{History, Reader, VCont, Value};
+///
+/// let data = Reader::new()
+///     .data_from_stream("a,b\n1,2".as_bytes())
+///     .expect("Failed to read data");
+/// let mut history = History::new(data).depth(10);
+/// history.set_cell(0, 0, Value::Text("3".to_string())).expect("Failed to set cell");
+/// history.begin_group();
+/// history.insert_row(1, None).expect("Failed to insert row");
+/// history.delete_column(1).expect("Failed to delete column");
+/// history.end_group();
+///
+/// history.undo().expect("Failed to undo");
+/// assert_eq!(history.get_column_count(), 2);
+/// history.undo().expect("Failed to undo");
+/// assert_eq!(history.get_cell(0, 0).unwrap().to_string(), "1");
+/// history.redo().expect("Failed to redo");
+/// assert_eq!(history.get_cell(0, 0).unwrap().to_string(), "3");
+/// ```
+#[derive(Clone)]
+pub struct History<T: Recordable> {
+    data: T,
+    undo_stack: VecDeque<Vec<Operation<T>>>,
+    redo_stack: Vec<Vec<Operation<T>>>,
+    depth: usize,
+    group: Option<Vec<Operation<T>>>,
+    group_level: usize,
+}
+
+impl<T: Recordable> History<T> {
+    /// Create a history of a container
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            depth: HISTORY_DEPTH,
+            group: None,
+            group_level: 0,
+        }
+    }
+
+    /// Set a maximum count of undoable steps
+    ///
+    /// Oldest steps are dropped when history exceeds depth.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self.trim();
+        self
+    }
+
+    /// Get a container
+    pub fn get_data(&self) -> &T {
+        &self.data
+    }
+
+    /// Take a container out of history
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    /// Start grouping edits into a single step
+    ///
+    /// Groups can be nested and only the outermost group makes a step.
+    pub fn begin_group(&mut self) {
+        if self.group_level == 0 {
+            self.group = Some(vec![]);
+        }
+        self.group_level += 1;
+    }
+
+    /// Finish grouping edits
+    ///
+    /// Empty group doesn't make a step.
+    pub fn end_group(&mut self) {
+        if self.group_level == 0 {
+            return;
+        }
+        self.group_level -= 1;
+        if self.group_level == 0 {
+            if let Some(step) = self.group.take() {
+                self.push_step(step);
+            }
+        }
+    }
+
+    /// Apply an arbitrary edit to a container and record it as a snapshot
+    ///
+    /// This is useful for edits which are not a part of VCont such as set_limiter. Snapshot is a
+    /// single copy of a whole container which undo and redo swap with a current one, thus VCont
+    /// methods of history are preferred for large containers. Container is not modified when
+    /// edit fails.
+    pub fn record<R>(&mut self, edit: impl FnOnce(&mut T) -> DcsvResult<R>) -> DcsvResult<R> {
+        let mut data = self.data.clone();
+        let result = edit(&mut data)?;
+        std::mem::swap(&mut self.data, &mut data);
+        self.push(
+            vec![],
+            Operation::Snapshot {
+                data: Box::new(data),
+            },
+        );
+        Ok(result)
+    }
+
+    /// Check if there is a step to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Check if there is a step to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Undo a last step
+    ///
+    /// This returns false when there is nothing to undo. Step is rolled back when undo fails.
+    pub fn undo(&mut self) -> DcsvResult<bool> {
+        self.end_all_groups();
+        let Some(mut step) = self.undo_stack.pop_back() else {
+            return Ok(false);
+        };
+        if let Err(err) = Self::run(&mut self.data, &mut step, true) {
+            self.undo_stack.push_back(step);
+            return Err(err);
+        }
+        self.redo_stack.push(step);
+        Ok(true)
+    }
+
+    /// Redo a last undone step
+    ///
+    /// This returns false when there is nothing to redo. Step is rolled back when redo fails.
+    pub fn redo(&mut self) -> DcsvResult<bool> {
+        self.end_all_groups();
+        let Some(mut step) = self.redo_stack.pop() else {
+            return Ok(false);
+        };
+        if let Err(err) = Self::run(&mut self.data, &mut step, false) {
+            self.redo_stack.push(step);
+            return Err(err);
+        }
+        self.undo_stack.push_back(step);
+        Ok(true)
+    }
+
+    /// Undo or redo operations of a step in place
+    ///
+    /// Operations which were already applied are reverted in reverse order when an operation
+    /// fails. They revert to states which existed before, thus reverting is not expected to fail.
+    fn run(data: &mut T, step: &mut [Operation<T>], undo: bool) -> DcsvResult<()> {
+        let order = if undo {
+            (0..step.len()).rev().collect::<Vec<_>>()
+        } else {
+            (0..step.len()).collect()
+        };
+        for (done, index) in order.iter().enumerate() {
+            if let Err(err) = step[*index].run(data, undo) {
+                for index in order[..done].iter().rev() {
+                    let _ = step[*index].run(data, !undo);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Clear undo and redo stacks
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        if self.group.is_some() {
+            self.group = Some(vec![]);
+        }
+    }
+
+    /// Record an operation into a current group or as a new step
+    ///
+    /// Formulas which an edit of an operation lost are restored after the operation is undone,
+    /// thus they come before the operation.
+    fn push(&mut self, formulas: Vec<(usize, usize, Expression)>, operation: Operation<T>) {
+        let mut operations = vec![];
+        if !formulas.is_empty() {
+            operations.push(Operation::Formulas { formulas });
+        }
+        operations.push(operation);
+        match self.group.as_mut() {
+            Some(group) => group.extend(operations),
+            None => self.push_step(operations),
+        }
+    }
+
+    fn push_step(&mut self, step: Vec<Operation<T>>) {
+        if step.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        self.undo_stack.push_back(step);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.undo_stack.len() > self.depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    fn end_all_groups(&mut self) {
+        while self.group_level > 0 {
+            self.end_group();
+        }
+    }
+
+    /// Get values of a row
+    fn row_values(&self, row: usize) -> Vec<Value> {
+        (0..self.data.get_column_count())
+            .filter_map(|column| self.data.get_cell(row, column).cloned())
+            .collect()
+    }
+
+    /// Apply an edit and record an operation with formulas which the edit loses
+    fn apply(
+        &mut self,
+        formulas: Vec<(usize, usize, Expression)>,
+        edit: impl FnOnce(&mut T) -> DcsvResult<()>,
+        operation: impl FnOnce(&Self) -> Operation<T>,
+    ) -> DcsvResult<()> {
+        edit(&mut self.data)?;
+        let operation = operation(self);
+        self.push(formulas, operation);
+        Ok(())
+    }
+}
+
+impl<T: Recordable> VCont for History<T> {
+    /// Create a history of an empty container
+    fn new() -> Self {
+        Self::new(T::new())
+    }
+
+    fn move_row(&mut self, src_index: usize, target_index: usize) -> DcsvResult<()> {
+        self.apply(
+            vec![],
+            |data| data.move_row(src_index, target_index),
+            |_| Operation::MoveRow {
+                from: src_index,
+                to: target_index,
+            },
+        )
+    }
+
+    fn sort_rows(&mut self, keys: &[SortKey]) -> DcsvResult<()> {
+        let indices = row_order(&self.data, keys)?;
+        self.data.permute_rows(&indices);
+        self.push(vec![], Operation::SortRows { indices });
+        Ok(())
+    }
+
+    fn move_column(&mut self, src_index: usize, target_index: usize) -> DcsvResult<()> {
+        self.apply(
+            vec![],
+            |data| data.move_column(src_index, target_index),
+            |_| Operation::MoveColumn {
+                from: src_index,
+                to: target_index,
+            },
+        )
+    }
+
+    fn rename_column(&mut self, column_index: usize, new_name: &str) -> DcsvResult<()> {
+        let before = self
+            .data
+            .get_column_name(column_index)
+            .unwrap_or_default()
+            .to_string();
+        self.apply(
+            vec![],
+            |data| data.rename_column(column_index, new_name),
+            |_| Operation::RenameColumn {
+                column: column_index,
+                before,
+                after: new_name.to_string(),
+            },
+        )
+    }
+
+    fn set_column(&mut self, column_index: usize, value: Value) -> DcsvResult<()> {
+        let before = (0..self.data.get_row_count())
+            .filter_map(|row| match self.data.get_cell(row, column_index) {
+                Some(previous) if *previous != value => Some((row, column_index, previous.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let formulas = self
+            .data
+            .get_formulas(|_, column| column == column_index, false);
+        let after = value.clone();
+        self.apply(
+            formulas,
+            |data| data.set_column(column_index, value),
+            |_| Operation::SetColumn {
+                column: column_index,
+                value: after,
+                before,
+            },
+        )
+    }
+
+    fn edit_row(&mut self, row_index: usize, values: &[Option<Value>]) -> DcsvResult<()> {
+        let before = self.row_values(row_index);
+        let formulas = self.data.get_formulas(
+            |row, column| row == row_index && matches!(values.get(column), Some(Some(_))),
+            false,
+        );
+        self.apply(
+            formulas,
+            |data| data.edit_row(row_index, values),
+            |history| Operation::SetRow {
+                row: row_index,
+                before,
+                after: history.row_values(row_index),
+            },
+        )
+    }
+
+    fn set_row(&mut self, row_index: usize, values: &[Value]) -> DcsvResult<()> {
+        let before = self.row_values(row_index);
+        let formulas = self.data.get_formulas(|row, _| row == row_index, false);
+        self.apply(
+            formulas,
+            |data| data.set_row(row_index, values),
+            |history| Operation::SetRow {
+                row: row_index,
+                before,
+                after: history.row_values(row_index),
+            },
+        )
+    }
+
+    fn get_cell(&self, x: usize, y: usize) -> Option<&Value> {
+        self.data.get_cell(x, y)
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, value: Value) -> DcsvResult<()> {
+        let before = self.data.get_cell(x, y).cloned().unwrap_or_default();
+        let formulas = self
+            .data
+            .get_formulas(|row, column| (row, column) == (x, y), false);
+        self.apply(
+            formulas,
+            |data| data.set_cell(x, y, value),
+            |history| Operation::SetCell {
+                x,
+                y,
+                before,
+                after: history.data.get_cell(x, y).cloned().unwrap_or_default(),
+            },
+        )
+    }
+
+    fn insert_row(&mut self, row_index: usize, source: Option<&[Value]>) -> DcsvResult<()> {
+        self.apply(
+            vec![],
+            |data| data.insert_row(row_index, source),
+            |history| Operation::InsertRow {
+                row: row_index,
+                values: history.row_values(row_index),
+            },
+        )
+    }
+
+    fn delete_row(&mut self, row_index: usize) -> bool {
+        if row_index >= self.data.get_row_count() {
+            return false;
+        }
+        let values = self.row_values(row_index);
+        let formulas = self.data.get_formulas(|row, _| row == row_index, true);
+        let deleted = self.data.delete_row(row_index);
+        if deleted {
+            self.push(
+                formulas,
+                Operation::DeleteRow {
+                    row: row_index,
+                    values,
+                },
+            );
+        }
+        deleted
+    }
+
+    fn insert_column(&mut self, column_index: usize, column_name: &str) -> DcsvResult<()> {
+        self.apply(
+            vec![],
+            |data| data.insert_column(column_index, column_name),
+            |_| Operation::InsertColumn {
+                column: column_index,
+                name: column_name.to_string(),
+            },
+        )
+    }
+
+    fn delete_column(&mut self, column_index: usize) -> DcsvResult<()> {
+        let formulas = self
+            .data
+            .get_formulas(|_, column| column == column_index, true);
+        let removed = self.data.take_column(column_index)?;
+        self.push(
+            formulas,
+            Operation::DeleteColumn {
+                column: column_index,
+                removed: Some(removed),
+            },
+        );
+        Ok(())
+    }
+
+    fn get_row_count(&self) -> usize {
+        self.data.get_row_count()
+    }
+
+    fn get_column_count(&self) -> usize {
+        self.data.get_column_count()
+    }
+
+    /// Drop all data from a container
+    ///
+    /// Dropped container is kept as a snapshot for undo.
+    fn drop_data(&mut self) {
+        // Dropping data doesn't fail, thus result is always ok
+        let _ = self.record(|data| {
+            data.drop_data();
+            Ok(())
+        });
+    }
+
+    /// Apply a function to every value
+    ///
+    /// Only changed cells are recorded. Applied values are written as restore_cells does, thus
+    /// values are not modified when they violate key constraints.
+    fn apply_all<F: FnMut(&mut Value)>(&mut self, mut f: F) {
+        let mut before = vec![];
+        let mut after = vec![];
+        for row in 0..self.data.get_row_count() {
+            for column in 0..self.data.get_column_count() {
+                if let Some(value) = self.data.get_cell(row, column) {
+                    let mut applied = value.clone();
+                    f(&mut applied);
+                    if applied != *value {
+                        before.push((row, column, value.clone()));
+                        after.push((row, column, applied));
+                    }
+                }
+            }
+        }
+        if !after.is_empty() && self.data.restore_cells(&after).is_ok() {
+            self.push(vec![], Operation::SetCells { before, after });
+        }
+    }
+
+    fn update_width_global(&mut self) {
+        self.data.update_width_global();
+    }
+
+    fn get_formatted_string(&self, line_delimiter: &str, align_type: CellAlignType) -> String {
+        self.data.get_formatted_string(line_delimiter, align_type)
+    }
+
+    fn get_string_table(&self, align_type: CellAlignType) -> Vec<Vec<String>> {
+        self.data.get_string_table(align_type)
+    }
+
+    /// Apply edits as a transaction which is recorded as a single step
+    ///
+    /// Edits are applied in place without copying a container. When closure fails, recorded
+    /// edits of the transaction are undone and dropped. Calling undo, redo or end_group inside
+    /// a closure finishes the transaction's group, thus edits before it are not reverted.
+    fn transaction<R, F>(&mut self, edit: F) -> DcsvResult<R>
+    where
+        Self: Sized + Clone,
+        F: FnOnce(&mut Self) -> DcsvResult<R>,
+    {
+        self.begin_group();
+        let level = self.group_level;
+        // Operations of an enclosing group are not a part of the transaction
+        let start = self.group.as_ref().map_or(0, |group| group.len());
+        let result = edit(self);
+        if result.is_err() && self.group_level == level {
+            if let Some(group) = self.group.as_mut() {
+                let mut operations = group.split_off(start.min(group.len()));
+                // Reverting recorded edits restores states which existed before, thus it is not
+                // expected to fail
+                let _ = Self::run(&mut self.data, &mut operations, true);
+            }
+        }
+        // Groups which closure left open end with the transaction
+        while self.group_level >= level {
+            self.end_group();
+        }
+        result
+    }
+}
@@ -0,0 +1,343 @@
+//! Join of two containers
+//!
+//! Join is a hash join on key columns. Keys are matched by text form, thus a number 1 matches a
+//! text "1". Key with an empty value never matches, which is similar to null of sql.
+//!
+//! Result has left columns followed by right columns except right key columns. Clashing column
+//! names get suffixes and a suffixed name which still clashes gets a number, e.g. "id_left_2".
+//! Columns which can be filled with empty values or with values of another column become text
+//! columns without limiters.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::meta::Meta;
+use crate::value::{Value, ValueType};
+use crate::vcont::VCont;
+use crate::virtual_array::VirtualArray;
+use crate::virtual_data::{Column, VirtualData};
+use std::collections::{HashMap, HashSet};
+use unicode_width::UnicodeWidthStr;
+
+/// Kind of a join
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only matched rows
+    Inner,
+    /// Every left row with matched right rows
+    Left,
+    /// Every right row with matched left rows
+    Right,
+    /// Every row of both sides
+    Full,
+    /// Left rows which don't match any right row
+    Anti,
+}
+
+/// Options for a join
+///
+/// # Usage
+///
+/// ```rust
+/// use dcsv::{JoinKind, JoinOption};
+///
+/// let option = JoinOption::new(JoinKind::Left).suffixes("_order", "_customer");
+/// ```
+#[derive(Clone, Debug)]
+pub struct JoinOption {
+    pub kind: JoinKind,
+    pub left_suffix: String,
+    pub right_suffix: String,
+}
+
+impl JoinOption {
+    /// Create an option with default suffixes of "_left" and "_right"
+    pub fn new(kind: JoinKind) -> Self {
+        Self {
+            kind,
+            left_suffix: "_left".to_string(),
+            right_suffix: "_right".to_string(),
+        }
+    }
+
+    /// Set suffixes for clashing column names
+    pub fn suffixes(mut self, left: &str, right: &str) -> Self {
+        self.left_suffix = left.to_string();
+        self.right_suffix = right.to_string();
+        self
+    }
+}
+
+/// Borrowed form of a container
+struct Table<'a> {
+    columns: &'a [Column],
+    rows: Vec<Vec<&'a Value>>,
+    keys: Vec<usize>,
+}
+
+impl<'a> Table<'a> {
+    fn new(
+        columns: &'a [Column],
+        rows: Vec<Vec<&'a Value>>,
+        keys: &[impl AsRef<str>],
+    ) -> DcsvResult<Self> {
+        let keys = keys
+            .iter()
+            .map(|key| {
+                let key = key.as_ref();
+                match key.parse::<usize>() {
+                    Ok(index) if index < columns.len() => Some(index),
+                    Ok(_) => None,
+                    Err(_) => columns.iter().position(|c| c.name == key),
+                }
+                .ok_or_else(|| {
+                    DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", key))
+                })
+            })
+            .collect::<DcsvResult<Vec<_>>>()?;
+        Ok(Self {
+            columns,
+            rows,
+            keys,
+        })
+    }
+
+    /// Get a key of a row in text form which can match
+    fn key_of(&self, row: usize) -> Option<Vec<String>> {
+        let key = self
+            .keys
+            .iter()
+            .map(|k| self.rows[row][*k])
+            .collect::<Vec<_>>();
+        if key.iter().any(|v| v.is_empty()) {
+            return None;
+        }
+        Some(key.iter().map(|v| v.to_string()).collect())
+    }
+}
+
+/// Join two tables into columns and rows
+fn join_tables(
+    left: &Table,
+    right: &Table,
+    option: &JoinOption,
+) -> DcsvResult<(Vec<Column>, Vec<Vec<Value>>)> {
+    if left.keys.is_empty() || left.keys.len() != right.keys.len() {
+        return Err(DcsvError::InvalidColumn(
+            "Join needs same count of key columns for both sides".to_string(),
+        ));
+    }
+    let kind = option.kind;
+    let right_columns = (0..right.columns.len())
+        .filter(|c| !right.keys.contains(c))
+        .collect::<Vec<_>>();
+
+    // Columns
+    let left_names = left
+        .columns
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<HashSet<_>>();
+    let right_names = right_columns
+        .iter()
+        .map(|c| right.columns[*c].name.as_str())
+        .collect::<HashSet<_>>();
+    let loosen = |column: &Column, name: String, loose: bool| {
+        if loose {
+            let mut loose = Column::new(&name, ValueType::Text, None);
+            loose.description = column.description.clone();
+            loose.unit = column.unit.clone();
+            loose
+        } else {
+            let mut column = column.clone();
+            column.name = name;
+            column
+        }
+    };
+    let left_loose = matches!(kind, JoinKind::Right | JoinKind::Full);
+    let right_loose = matches!(kind, JoinKind::Left | JoinKind::Full);
+    // Suffixed name can clash with another column, thus names are deduplicated in order
+    let mut used = HashSet::new();
+    let mut unique = |name: String| {
+        let mut candidate = name.clone();
+        let mut count = 2;
+        while !used.insert(candidate.clone()) {
+            candidate = format!("{}_{}", name, count);
+            count += 1;
+        }
+        candidate
+    };
+    let mut columns = left
+        .columns
+        .iter()
+        .map(|c| {
+            let name = if kind != JoinKind::Anti && right_names.contains(c.name.as_str()) {
+                format!("{}{}", c.name, option.left_suffix)
+            } else {
+                c.name.clone()
+            };
+            loosen(c, unique(name), left_loose)
+        })
+        .collect::<Vec<_>>();
+    if kind != JoinKind::Anti {
+        for index in &right_columns {
+            let column = &right.columns[*index];
+            let name = if left_names.contains(column.name.as_str()) {
+                format!("{}{}", column.name, option.right_suffix)
+            } else {
+                column.name.clone()
+            };
+            columns.push(loosen(column, unique(name), right_loose));
+        }
+    }
+
+    // Rows
+    let value_of = |value: &Value, loose: bool| {
+        if loose {
+            Value::Text(value.to_string())
+        } else {
+            value.clone()
+        }
+    };
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for row in 0..right.rows.len() {
+        if let Some(key) = right.key_of(row) {
+            index.entry(key).or_default().push(row);
+        }
+    }
+    let mut rows = vec![];
+    let mut matched = vec![false; right.rows.len()];
+    for (row, values) in left.rows.iter().enumerate() {
+        let matches = left
+            .key_of(row)
+            .and_then(|key| index.get(&key))
+            .map(|rows| rows.as_slice())
+            .unwrap_or_default();
+        let left_values = values
+            .iter()
+            .map(|v| value_of(v, left_loose))
+            .collect::<Vec<_>>();
+        match kind {
+            JoinKind::Anti => {
+                if matches.is_empty() {
+                    rows.push(left_values);
+                }
+                continue;
+            }
+            JoinKind::Left | JoinKind::Full if matches.is_empty() => {
+                let mut joined = left_values;
+                joined.extend(right_columns.iter().map(|_| Value::default()));
+                rows.push(joined);
+                continue;
+            }
+            _ => (),
+        }
+        for right_row in matches {
+            matched[*right_row] = true;
+            let mut joined = left_values.clone();
+            joined.extend(
+                right_columns
+                    .iter()
+                    .map(|c| value_of(right.rows[*right_row][*c], right_loose)),
+            );
+            rows.push(joined);
+        }
+    }
+    if matches!(kind, JoinKind::Right | JoinKind::Full) {
+        for (row, values) in right.rows.iter().enumerate() {
+            if matched[row] {
+                continue;
+            }
+            let mut joined = vec![Value::default(); left.columns.len()];
+            for (left_key, right_key) in left.keys.iter().zip(&right.keys) {
+                joined[*left_key] = Value::Text(values[*right_key].to_string());
+            }
+            joined.extend(
+                right_columns
+                    .iter()
+                    .map(|c| value_of(values[*c], right_loose)),
+            );
+            rows.push(joined);
+        }
+    }
+    Ok((columns, rows))
+}
+
+impl VirtualData {
+    /// Join with another virtual data on key columns
+    ///
+    /// Key columns can be either column names or column indices. Right join and full join put
+    /// unmatched right rows after left rows.
+    pub fn join(
+        &self,
+        other: &VirtualData,
+        left_on: &[impl AsRef<str>],
+        right_on: &[impl AsRef<str>],
+        option: &JoinOption,
+    ) -> DcsvResult<VirtualData> {
+        let left = Table::new(&self.columns, data_rows(self)?, left_on)?;
+        let right = Table::new(&other.columns, data_rows(other)?, right_on)?;
+        let (columns, rows) = join_tables(&left, &right, option)?;
+
+        let mut data = VirtualData::new();
+        for (index, column) in columns.into_iter().enumerate() {
+            data.insert_column_with_type(
+                index,
+                &column.name,
+                column.column_type,
+                Some(column.limiter),
+                None,
+            )?;
+            data.columns[index].description = column.description;
+            data.columns[index].unit = column.unit;
+        }
+        for (index, row) in rows.iter().enumerate() {
+            data.insert_row(index, Some(row))?;
+        }
+        data.mark_clean();
+        Ok(data)
+    }
+}
+
+impl VirtualArray {
+    /// Join with another virtual array on key columns
+    ///
+    /// Key columns can be either column names or column indices. Right join and full join put
+    /// unmatched right rows after left rows.
+    pub fn join(
+        &self,
+        other: &VirtualArray,
+        left_on: &[impl AsRef<str>],
+        right_on: &[impl AsRef<str>],
+        option: &JoinOption,
+    ) -> DcsvResult<VirtualArray> {
+        let left = Table::new(&self.columns, array_rows(self), left_on)?;
+        let right = Table::new(&other.columns, array_rows(other), right_on)?;
+        let (columns, rows) = join_tables(&left, &right, option)?;
+
+        let mut array = VirtualArray::new();
+        array.metas = columns
+            .iter()
+            .map(|c| {
+                let mut meta = Meta::new();
+                meta.set_width(UnicodeWidthStr::width(c.name.as_str()));
+                meta
+            })
+            .collect();
+        array.columns = columns.iter().map(|c| Column::empty(&c.name)).collect();
+        array.rows = rows;
+        array.update_width_global();
+        array.mark_clean();
+        Ok(array)
+    }
+}
+
+/// Get rows of virtual data in column order
+fn data_rows(data: &VirtualData) -> DcsvResult<Vec<Vec<&Value>>> {
+    (0..data.get_row_count())
+        .map(|row| data.get_row_iterator(row).map(|i| i.collect()))
+        .collect()
+}
+
+/// Get rows of virtual array
+fn array_rows(array: &VirtualArray) -> Vec<Vec<&Value>> {
+    array.rows.iter().map(|row| row.iter().collect()).collect()
+}
@@ -32,23 +32,67 @@
 //!
 //! // Refer docs.rs for various VirtualData methods
 //! let value : &Value = data.get_cell(1,1).expect("Failed to get cell");
+mod aggregate;
+mod comparator;
+mod computed;
+mod concat;
+mod constraint;
+mod dedup;
+mod diff;
 mod error;
+mod expression;
+mod formula;
+mod history;
+mod join;
 mod meta;
+mod migration;
 mod parser;
+mod pivot;
+mod query;
 mod reader;
+mod schema;
+mod search;
+mod sort;
+mod table_schema;
 mod test;
+mod tracking;
 pub mod utils;
+mod validation;
+mod validator;
 mod value;
 mod vcont;
 mod virtual_array;
 mod virtual_data;
 
+pub use aggregate::{Aggregate, AggregateFn};
+pub use comparator::Comparator;
+pub use computed::DerivedColumn;
+pub use concat::{ConcatOption, ConcatReport, LimiterConflict};
+pub use constraint::{DanglingReference, ForeignKey, KeyConstraint, KeyKind};
+pub use dedup::Keep;
+pub use diff::{DataChange, DataDiff};
 pub use error::{DcsvError, DcsvResult};
+pub use expression::Expression;
+pub use history::{History, Recordable};
+pub use join::{JoinKind, JoinOption};
+pub use migration::{SchemaChange, SchemaDiff};
+pub use query::Query;
 pub use reader::{Reader, ReaderOption};
+pub use schema::{InferOption, Schema};
+pub use search::{ReplaceReport, Scope};
+pub use sort::{NullOrder, SortKey, SortOrder};
+pub use tracking::{ChangeState, Changes};
 
-pub use value::LIMITER_ATTRIBUTE_LEN;
-pub use virtual_data::SCHEMA_HEADER;
+pub use formula::{FORMULA_REF_ERROR, FORMULA_VALUE_ERROR};
+pub use history::HISTORY_DEPTH;
+pub use pivot::{MELT_VALUE, MELT_VARIABLE};
+pub use schema::{EXTENDED_SCHEMA_HEADER, SCHEMA_HEADER};
+pub use value::{EXTENDED_LIMITER_ATTRIBUTE_LEN, LIMITER_ATTRIBUTE_LEN};
 
+pub use validation::{LimiterMode, ValidationReport, Violation, ViolationRule};
+pub use validator::{
+    RowValidator, RowValidatorFn, RowViolation, Validator, ValidatorFn, ValidatorRegistry,
+};
 pub use value::{Value, ValueLimiter, ValueType};
 pub use vcont::CellAlignType;
 pub use vcont::VCont;
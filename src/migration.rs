@@ -0,0 +1,356 @@
+//! Schema diff and migration
+//!
+//! Diff compares a source schema with a target schema column by column. Columns are matched by
+//! names, thus a renamed column should be given as a rename hint. Otherwise it is listed as a
+//! removed column and an added column.
+//!
+//! Migration applies a diff to virtual data with VCont operations.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::schema::Schema;
+use crate::validation::{LimiterMode, ValidationReport};
+use crate::value::{ValueLimiter, ValueType};
+use crate::vcont::VCont;
+use crate::virtual_data::{Column, VirtualData};
+use std::collections::{HashMap, HashSet};
+
+/// A single column change between two schemas
+///
+/// Changes of a renamed column refer to the new name.
+#[derive(Clone, Debug)]
+pub enum SchemaChange {
+    /// Column only exists in target
+    Added(Column),
+    /// Column only exists in source
+    Removed(String),
+    /// Column is renamed
+    Renamed { from: String, to: String },
+    /// Column has a different position among columns which exist in both schemas
+    Moved {
+        column: String,
+        from: usize,
+        to: usize,
+    },
+    /// Column has a different type
+    Retyped {
+        column: String,
+        from: ValueType,
+        to: ValueType,
+        limiter: ValueLimiter,
+    },
+    /// Column has a same type but different limiter rules
+    Reconstrained {
+        column: String,
+        limiter: ValueLimiter,
+    },
+}
+
+impl std::fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added(column) => write!(f, "+ {} ({})", column.name, column.column_type),
+            Self::Removed(column) => write!(f, "- {}", column),
+            Self::Renamed { from, to } => write!(f, "~ {} -> {}", from, to),
+            Self::Moved { column, from, to } => write!(f, "~ {} : {} -> {}", column, from, to),
+            Self::Retyped {
+                column, from, to, ..
+            } => write!(f, "~ {} : {} -> {}", column, from, to),
+            Self::Reconstrained { column, .. } => write!(f, "~ {} : limiter", column),
+        }
+    }
+}
+
+/// Difference between two schemas
+#[derive(Clone, Debug, Default)]
+pub struct SchemaDiff {
+    pub changes: Vec<SchemaChange>,
+    /// Column names of target schema in order
+    pub columns: Vec<String>,
+}
+
+impl SchemaDiff {
+    /// Check if schemas have no difference
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Get count of changes
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Get iterator of changes
+    pub fn iter(&self) -> std::slice::Iter<'_, SchemaChange> {
+        self.changes.iter()
+    }
+}
+
+impl std::fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .changes
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Schema {
+    /// Compare schema with a target schema
+    pub fn diff(&self, target: &Schema) -> SchemaDiff {
+        // Diff without renames doesn't fail
+        self.diff_with_renames(target, &[] as &[(&str, &str)])
+            .unwrap()
+    }
+
+    /// Compare schema with a target schema with rename hints
+    ///
+    /// Each hint is a pair of a source column name and a target column name.
+    pub fn diff_with_renames(
+        &self,
+        target: &Schema,
+        renames: &[(impl AsRef<str>, impl AsRef<str>)],
+    ) -> DcsvResult<SchemaDiff> {
+        let mut names = HashMap::new();
+        for (from, to) in renames {
+            let (from, to) = (from.as_ref(), to.as_ref());
+            if self.get_column(from).is_none() || target.get_column(to).is_none() {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "Cannot rename \"{}\" to \"{}\" which doesn't exist in schema",
+                    from, to
+                )));
+            }
+            names.insert(from, to);
+        }
+
+        let mut changes = vec![];
+        let mut matched = HashSet::new();
+        // Source column names which survive, in source order
+        let mut survivors = vec![];
+        for col in &self.columns {
+            let name = names.get(col.name.as_str()).copied().unwrap_or(&col.name);
+            let target_col = match target.get_column(name) {
+                Some(target_col) => target_col,
+                None => {
+                    changes.push(SchemaChange::Removed(col.name.clone()));
+                    continue;
+                }
+            };
+            if !matched.insert(name) {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "Multiple columns are mapped to \"{}\"",
+                    name
+                )));
+            }
+            survivors.push(name);
+            if name != col.name {
+                changes.push(SchemaChange::Renamed {
+                    from: col.name.clone(),
+                    to: name.to_string(),
+                });
+            }
+            let (from, to) = (col.limiter.get_type(), target_col.limiter.get_type());
+            if from != to {
+                changes.push(SchemaChange::Retyped {
+                    column: name.to_string(),
+                    from,
+                    to,
+                    limiter: target_col.limiter.clone(),
+                });
+            } else if col.limiter != target_col.limiter {
+                changes.push(SchemaChange::Reconstrained {
+                    column: name.to_string(),
+                    limiter: target_col.limiter.clone(),
+                });
+            }
+        }
+
+        let target_order = target
+            .columns
+            .iter()
+            .filter(|c| matched.contains(c.name.as_str()))
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        for (from, name) in survivors.iter().enumerate() {
+            // It is safe to unwrap because survivors and target order have same names
+            let to = target_order.iter().position(|n| n == name).unwrap();
+            if from != to {
+                changes.push(SchemaChange::Moved {
+                    column: name.to_string(),
+                    from,
+                    to,
+                });
+            }
+        }
+
+        for col in &target.columns {
+            if !matched.contains(col.name.as_str()) {
+                changes.push(SchemaChange::Added(col.clone()));
+            }
+        }
+
+        Ok(SchemaDiff {
+            changes,
+            columns: target.columns.iter().map(|c| c.name.clone()).collect(),
+        })
+    }
+}
+
+impl VirtualData {
+    /// Migrate virtual data with a schema diff
+    ///
+    /// Columns are renamed, removed, added with limiter's default value, retyped and reordered to
+    /// target schema's order. Columns which don't exist in target schema stay after target
+    /// columns. Unqualified values of retyped or reconstrained columns are treated by mode.
+    ///
+    /// Every change is validated before data is modified, thus data is migrated in place and is
+    /// not modified when migration fails. Renames are applied at once, thus columns can swap
+    /// their names.
+    pub fn migrate(
+        &mut self,
+        diff: &SchemaDiff,
+        mode: LimiterMode,
+    ) -> DcsvResult<ValidationReport> {
+        let mut report = ValidationReport::new();
+
+        let mut removed = vec![];
+        for change in &diff.changes {
+            if let SchemaChange::Removed(column) = change {
+                self.get_column_position(column)?;
+                removed.push(column.clone());
+            }
+        }
+
+        // Pairs of a current name and a migrated name of surviving columns
+        let mut names = self
+            .columns
+            .iter()
+            .filter(|c| !removed.contains(&c.name))
+            .map(|c| (c.name.clone(), c.name.clone()))
+            .collect::<Vec<_>>();
+        let mut renames = vec![];
+        for change in &diff.changes {
+            if let SchemaChange::Renamed { from, to } = change {
+                let name = names
+                    .iter_mut()
+                    .find(|(current, migrated)| current == from && migrated == from)
+                    .ok_or_else(|| {
+                        DcsvError::InvalidColumn(format!("Cannot rename column \"{}\"", from))
+                    })?;
+                name.1 = to.clone();
+                renames.push((from.as_str(), to.as_str()));
+            }
+        }
+
+        let mut migrated = HashSet::new();
+        for (_, name) in &names {
+            if !migrated.insert(name.as_str()) {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "Multiple columns are migrated to \"{}\"",
+                    name
+                )));
+            }
+        }
+        for change in &diff.changes {
+            if let SchemaChange::Added(column) = change {
+                // Numeric name is treated as a column index by insertion
+                let is_index = column
+                    .name
+                    .parse::<usize>()
+                    .is_ok_and(|index| index < migrated.len());
+                if is_index || !migrated.insert(column.name.as_str()) {
+                    return Err(DcsvError::InvalidColumn(format!(
+                        "Cannot add existing column = \"{}\"",
+                        column.name
+                    )));
+                }
+            }
+        }
+        if diff.columns.len() > migrated.len()
+            || diff.columns.iter().collect::<HashSet<_>>().len() != diff.columns.len()
+            || diff.columns.iter().any(|c| !migrated.contains(c.as_str()))
+        {
+            return Err(DcsvError::InvalidColumn(
+                "Target columns don't match migrated columns".to_string(),
+            ));
+        }
+
+        // Conversions are computed with current names
+        let mut conversions = vec![];
+        let mut limiters = vec![];
+        for change in &diff.changes {
+            if let SchemaChange::Retyped {
+                column, limiter, ..
+            }
+            | SchemaChange::Reconstrained { column, limiter } = change
+            {
+                let (current, _) = names.iter().find(|(_, m)| m == column).ok_or_else(|| {
+                    DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", column))
+                })?;
+                let index = self.get_column_position(current)?;
+                let (updates, applied) = self.conform_column(index, limiter, mode)?;
+                report.violations.extend(applied.violations);
+                conversions.push((current.clone(), updates));
+                limiters.push((column.as_str(), limiter));
+            }
+        }
+        self.check_converted_keys(&conversions, &removed, &[])?;
+
+        // Changes are validated above, thus following operations don't fail
+        for column in &removed {
+            let index = self.get_column_position(column)?;
+            self.delete_column(index)?;
+        }
+        let mut temporaries = vec![];
+        for (from, to) in renames {
+            let index = self.get_column_position(from)?;
+            let mut temporary = format!("\u{0}{}", from);
+            while self.get_column_position(&temporary).is_ok() {
+                temporary.insert(0, '\u{0}');
+            }
+            self.rename_column_unchecked(index, &temporary);
+            temporaries.push((temporary, to));
+        }
+        for (temporary, to) in temporaries {
+            let index = self.get_column_position(&temporary)?;
+            self.rename_column_unchecked(index, to);
+        }
+        for change in &diff.changes {
+            if let SchemaChange::Added(column) = change {
+                self.insert_column_with_type(
+                    self.get_column_count(),
+                    &column.name,
+                    column.limiter.get_type(),
+                    Some(column.limiter.clone()),
+                    None,
+                )?;
+                let index = self.get_column_count() - 1;
+                self.columns[index].description = column.description.clone();
+                self.columns[index].unit = column.unit.clone();
+            }
+        }
+        for ((column, limiter), (_, updates)) in limiters.into_iter().zip(conversions) {
+            let index = self.get_column_position(column)?;
+            self.apply_conformed(index, limiter, updates);
+        }
+
+        for (target, name) in diff.columns.iter().enumerate() {
+            let index = self.get_column_position(name)?;
+            if index != target {
+                self.move_column(index, target)?;
+            }
+        }
+
+        report.violations.sort_by_key(|v| v.row);
+        Ok(report)
+    }
+
+    /// Get an index of a column by exact name
+    pub(crate) fn get_column_position(&self, name: &str) -> DcsvResult<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", name)))
+    }
+}
@@ -74,18 +74,20 @@ impl Parser {
                     }
                 }
                 '"' => {
-                    // Add literal double quote if previous was same character
-                    if previous == '"' {
-                        previous = ' '; // Reset previous
-                    } else {
-                        if let Some('"') = iter.peek() {
-                        } else {
-                            self.on_quote = !self.on_quote;
+                    // Two double quotes inside a quote is a literal double quote
+                    if self.on_quote && iter.peek() == Some(&'"') {
+                        iter.next();
+                        if !consume_dquote {
+                            value.push(ch);
                         }
+                        value.push(ch);
                         previous = ch;
-                        if consume_dquote {
-                            continue;
-                        }
+                        continue;
+                    }
+                    self.on_quote = !self.on_quote;
+                    previous = ch;
+                    if consume_dquote {
+                        continue;
                     }
                 }
                 _ => previous = ch,
@@ -0,0 +1,216 @@
+//! Pivot and melt
+//!
+//! Pivot turns a long layout into a wide layout by spreading values of a column into new
+//! columns. Melt does the opposite and gathers value columns into variable and value columns.
+
+use crate::aggregate::{Aggregate, AggregateFn};
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::{Value, ValueLimiter, ValueType};
+use crate::vcont::VCont;
+use crate::virtual_data::VirtualData;
+use std::collections::HashMap;
+
+/// Name of a column which has names of melted columns
+pub const MELT_VARIABLE: &str = "variable";
+/// Name of a column which has values of melted columns
+pub const MELT_VALUE: &str = "value";
+
+impl VirtualData {
+    /// Spread values of a column into new columns
+    ///
+    /// Rows are grouped by index columns and each distinct value of pivot column becomes a new
+    /// column named "{values}_{pivot value}". Values of a cell are reduced by an aggregate
+    /// function, thus cell type follows group by's rule. Rows with an empty pivot value are
+    /// ignored.
+    ///
+    /// Groups and new columns keep an order of their first appearance. Every row is a single
+    /// group when index columns are empty. Columns can be either column names or column indices.
+    pub fn pivot(
+        &self,
+        index: &[impl AsRef<str>],
+        columns: &str,
+        values: &str,
+        aggregate: AggregateFn,
+    ) -> DcsvResult<VirtualData> {
+        let keys = if index.is_empty() {
+            vec![]
+        } else {
+            self.get_column_names(index)?
+        };
+        let keys = keys
+            .iter()
+            .map(|name| self.get_column_position(name))
+            .collect::<DcsvResult<Vec<_>>>()?;
+        let [pivot, target] = [columns, values].map(|column| {
+            self.try_get_column_index(column).ok_or_else(|| {
+                DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", column))
+            })
+        });
+        let (pivot, target) = (pivot?, target?);
+        let aggregate = Aggregate::new(values, aggregate);
+
+        // Collect cells of groups and pivot values
+        let mut groups: Vec<Vec<&Value>> = vec![];
+        let mut group_index = HashMap::new();
+        let mut pivots: Vec<&Value> = vec![];
+        let mut pivot_index = HashMap::new();
+        let mut cells: HashMap<(usize, usize), Vec<&Value>> = HashMap::new();
+        for row in 0..self.get_row_count() {
+            // It is safe to unwrap because indices are already confirmed to exist
+            let pivot_value = self.get_cell(row, pivot).unwrap();
+            if pivot_value.is_empty() {
+                continue;
+            }
+            let key = keys
+                .iter()
+                .map(|k| self.get_cell(row, *k).unwrap())
+                .collect::<Vec<_>>();
+            let group = *group_index.entry(key.clone()).or_insert_with(|| {
+                groups.push(key);
+                groups.len() - 1
+            });
+            let column = *pivot_index.entry(pivot_value).or_insert_with(|| {
+                pivots.push(pivot_value);
+                pivots.len() - 1
+            });
+            cells
+                .entry((group, column))
+                .or_default()
+                .push(self.get_cell(row, target).unwrap());
+        }
+
+        let mut data = VirtualData::new();
+        for (position, key) in keys.iter().enumerate() {
+            let column = &self.columns[*key];
+            data.insert_column_with_type(
+                position,
+                &column.name,
+                column.column_type,
+                Some(column.limiter.clone()),
+                None,
+            )?;
+        }
+        let source = &self.columns[target];
+        let value_type = aggregate.result_type(source.column_type);
+        for pivot_value in &pivots {
+            let mut limiter = ValueLimiter::default();
+            limiter.set_type(value_type);
+            data.insert_column_with_type(
+                data.get_column_count(),
+                &format!("{}_{}", source.name, pivot_value),
+                value_type,
+                Some(limiter),
+                None,
+            )?;
+        }
+
+        for (group, key) in groups.iter().enumerate() {
+            let mut row = key.iter().map(|v| (*v).clone()).collect::<Vec<_>>();
+            for column in 0..pivots.len() {
+                let values = cells
+                    .get(&(group, column))
+                    .map(|v| v.as_slice())
+                    .unwrap_or_default();
+                row.push(aggregate.apply(source, values)?);
+            }
+            data.insert_row(group, Some(&row))?;
+        }
+        data.mark_clean();
+        Ok(data)
+    }
+
+    /// Gather value columns into variable and value columns
+    ///
+    /// Each row becomes a row per value column, which has id columns, a name of the value column
+    /// and its value. Every column except id columns is gathered when value columns are empty.
+    ///
+    /// Value column keeps a type and a limiter when every gathered column has the same one,
+    /// otherwise it becomes a text column. Columns can be either column names or column indices.
+    pub fn melt(
+        &self,
+        id_columns: &[impl AsRef<str>],
+        value_columns: &[impl AsRef<str>],
+    ) -> DcsvResult<VirtualData> {
+        let ids = if id_columns.is_empty() {
+            vec![]
+        } else {
+            self.get_column_names(id_columns)?
+        };
+        let gathered = if value_columns.is_empty() {
+            self.columns
+                .iter()
+                .map(|c| c.name.clone())
+                .filter(|name| !ids.contains(name))
+                .collect()
+        } else {
+            self.get_column_names(value_columns)?
+        };
+        if gathered.is_empty() {
+            return Err(DcsvError::InvalidColumn(
+                "At least one value column is required".to_string(),
+            ));
+        }
+        let ids = ids
+            .iter()
+            .map(|name| self.get_column_position(name))
+            .collect::<DcsvResult<Vec<_>>>()?;
+        let gathered = gathered
+            .iter()
+            .map(|name| self.get_column_position(name))
+            .collect::<DcsvResult<Vec<_>>>()?;
+
+        let mut data = VirtualData::new();
+        for (position, id) in ids.iter().enumerate() {
+            let column = &self.columns[*id];
+            data.insert_column_with_type(
+                position,
+                &column.name,
+                column.column_type,
+                Some(column.limiter.clone()),
+                None,
+            )?;
+        }
+        let first = &self.columns[gathered[0]];
+        let (value_type, limiter) = if gathered
+            .iter()
+            .all(|g| self.columns[*g].limiter == first.limiter)
+        {
+            (first.column_type, Some(first.limiter.clone()))
+        } else {
+            (ValueType::Text, None)
+        };
+        data.insert_column_with_type(
+            data.get_column_count(),
+            MELT_VARIABLE,
+            ValueType::Text,
+            None,
+            None,
+        )?;
+        data.insert_column_with_type(
+            data.get_column_count(),
+            MELT_VALUE,
+            value_type,
+            limiter,
+            None,
+        )?;
+
+        for row in 0..self.get_row_count() {
+            for column in &gathered {
+                // It is safe to unwrap because indices are already confirmed to exist
+                let mut values = ids
+                    .iter()
+                    .map(|id| self.get_cell(row, *id).unwrap().clone())
+                    .collect::<Vec<_>>();
+                values.push(Value::Text(self.columns[*column].name.clone()));
+                let value = self.get_cell(row, *column).unwrap();
+                values.push(match value_type {
+                    ValueType::Text => Value::Text(value.to_string()),
+                    ValueType::Number => value.clone(),
+                });
+                data.insert_row(data.get_row_count(), Some(&values))?;
+            }
+        }
+        data.mark_clean();
+        Ok(data)
+    }
+}
@@ -0,0 +1,458 @@
+//! Filter query for rows
+//!
+//! Query is a small expression language which is parsed into a predicate over a row.
+//!
+//! ```text
+//! age >= 30 && name ~ /^K/ || city in ("Seoul", "Busan")
+//! ```
+//!
+//! - Column is an identifier or a name quoted with backticks, e.g. `` `first name` ``
+//! - Literal is a number or a text quoted with double quotes
+//! - Comparison operators are ==, !=, <, <=, > and >=
+//! - ~ and !~ match a regex pattern which is quoted with slashes or double quotes
+//! - in matches any of literals in parentheses
+//! - && binds tighter than || and ! negates an expression
+//!
+//! Number literal is compared numerically with a value which can be parsed as a number. Such
+//! comparison with a non number value is false, except for != which is always a negation of ==.
+//! Thus `age != 30` matches a row whose age is not a number. Text literal is always compared with
+//! a value's text form lexicographically, thus `code == "007"` doesn't match "7".
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::Value;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// Parsed filter query
+#[derive(Clone, Debug)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    /// Parse a query from source text
+    pub fn parse(src: &str) -> DcsvResult<Self> {
+        let tokens = tokenize(src)?;
+        let mut parser = QueryParser { tokens, cursor: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(DcsvError::InvalidQuery(format!(
+                "Unexpected token \"{}\"",
+                token
+            )));
+        }
+        Ok(Self { expr })
+    }
+
+    /// Get column names which query refers to
+    pub fn get_columns(&self) -> Vec<&str> {
+        let mut columns = vec![];
+        self.expr.collect_columns(&mut columns);
+        let mut seen = HashSet::new();
+        columns.retain(|c| seen.insert(*c));
+        columns
+    }
+
+    /// Check if a row matches query
+    ///
+    /// Getter returns a value of a column name. Missing value is treated as an empty value.
+    pub fn matches<'a>(&self, get: impl Fn(&str) -> Option<&'a Value>) -> bool {
+        self.expr.eval(&get)
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = DcsvError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Literal {
+    Number(isize),
+    Text(String),
+}
+
+impl Literal {
+    /// Compare a value with literal
+    ///
+    /// This returns none when value is not comparable with literal.
+    fn compare(&self, value: &Value) -> Option<Ordering> {
+        match (self, value) {
+            (Self::Number(num), Value::Number(value)) => Some(value.cmp(num)),
+            (Self::Number(num), Value::Text(text)) => text
+                .trim()
+                .parse::<isize>()
+                .ok()
+                .map(|value| value.cmp(num)),
+            (Self::Text(text), value) => Some(value.to_string().as_str().cmp(text.as_str())),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        column: String,
+        operator: Operator,
+        literal: Literal,
+    },
+    Match {
+        column: String,
+        pattern: Regex,
+    },
+    In {
+        column: String,
+        literals: Vec<Literal>,
+    },
+}
+
+impl Expr {
+    fn collect_columns<'a>(&'a self, columns: &mut Vec<&'a str>) {
+        match self {
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => {
+                lhs.collect_columns(columns);
+                rhs.collect_columns(columns);
+            }
+            Self::Not(expr) => expr.collect_columns(columns),
+            Self::Compare { column, .. } | Self::Match { column, .. } | Self::In { column, .. } => {
+                columns.push(column)
+            }
+        }
+    }
+
+    fn eval<'a>(&self, get: &impl Fn(&str) -> Option<&'a Value>) -> bool {
+        let value_of = |column: &str| get(column).cloned().unwrap_or_default();
+        match self {
+            Self::And(lhs, rhs) => lhs.eval(get) && rhs.eval(get),
+            Self::Or(lhs, rhs) => lhs.eval(get) || rhs.eval(get),
+            Self::Not(expr) => !expr.eval(get),
+            Self::Compare {
+                column,
+                operator,
+                literal,
+            } => {
+                let ordering = literal.compare(&value_of(column));
+                match operator {
+                    Operator::Eq => ordering == Some(Ordering::Equal),
+                    Operator::Ne => ordering != Some(Ordering::Equal),
+                    Operator::Lt => ordering == Some(Ordering::Less),
+                    Operator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    Operator::Gt => ordering == Some(Ordering::Greater),
+                    Operator::Ge => {
+                        matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                    }
+                }
+            }
+            Self::Match { column, pattern } => pattern.is_match(&value_of(column).to_string()),
+            Self::In { column, literals } => {
+                let value = value_of(column);
+                literals
+                    .iter()
+                    .any(|l| l.compare(&value) == Some(Ordering::Equal))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Column(String),
+    Number(isize),
+    Text(String),
+    Regex(String),
+    Operator(Operator),
+    Match,
+    NotMatch,
+    In,
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Column(name) => write!(f, "{}", name),
+            Self::Number(num) => write!(f, "{}", num),
+            Self::Text(text) => write!(f, "\"{}\"", text),
+            Self::Regex(pattern) => write!(f, "/{}/", pattern),
+            Self::Operator(operator) => write!(
+                f,
+                "{}",
+                match operator {
+                    Operator::Eq => "==",
+                    Operator::Ne => "!=",
+                    Operator::Lt => "<",
+                    Operator::Le => "<=",
+                    Operator::Gt => ">",
+                    Operator::Ge => ">=",
+                }
+            ),
+            Self::Match => write!(f, "~"),
+            Self::NotMatch => write!(f, "!~"),
+            Self::In => write!(f, "in"),
+            Self::And => write!(f, "&&"),
+            Self::Or => write!(f, "||"),
+            Self::Not => write!(f, "!"),
+            Self::LParen => write!(f, "("),
+            Self::RParen => write!(f, ")"),
+            Self::Comma => write!(f, ","),
+        }
+    }
+}
+
+/// Split query source into tokens
+fn tokenize(src: &str) -> DcsvResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = src.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ' ' | '\t' | '\r' | '\n' => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '~' => Token::Match,
+            '&' | '|' => {
+                if chars.next_if_eq(&ch).is_none() {
+                    return Err(DcsvError::InvalidQuery(format!(
+                        "Expected \"{0}{0}\" but got single \"{0}\"",
+                        ch
+                    )));
+                }
+                if ch == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '!' => {
+                if chars.next_if_eq(&'=').is_some() {
+                    Token::Operator(Operator::Ne)
+                } else if chars.next_if_eq(&'~').is_some() {
+                    Token::NotMatch
+                } else {
+                    Token::Not
+                }
+            }
+            '=' => {
+                if chars.next_if_eq(&'=').is_none() {
+                    return Err(DcsvError::InvalidQuery(
+                        "Expected \"==\" but got single \"=\"".to_string(),
+                    ));
+                }
+                Token::Operator(Operator::Eq)
+            }
+            '<' | '>' => {
+                let equal = chars.next_if_eq(&'=').is_some();
+                Token::Operator(match (ch, equal) {
+                    ('<', false) => Operator::Lt,
+                    ('<', true) => Operator::Le,
+                    (_, false) => Operator::Gt,
+                    (_, true) => Operator::Ge,
+                })
+            }
+            '"' => Token::Text(take_quoted(&mut chars, '"')?),
+            '/' => Token::Regex(take_quoted(&mut chars, '/')?),
+            '`' => Token::Column(take_quoted(&mut chars, '`')?),
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                    number.push(c);
+                }
+                Token::Number(number.parse().map_err(|_| {
+                    DcsvError::InvalidQuery(format!("Invalid number \"{}\"", number))
+                })?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                if ident == "in" {
+                    Token::In
+                } else {
+                    Token::Column(ident)
+                }
+            }
+            c => {
+                return Err(DcsvError::InvalidQuery(format!(
+                    "Unexpected character \"{}\"",
+                    c
+                )))
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Take characters until a closing quote
+///
+/// Backslash escapes a quote character. Other escape sequences are kept as they are so that
+/// regex escapes survive.
+fn take_quoted(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    quote: char,
+) -> DcsvResult<String> {
+    let mut text = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(next) if next == quote => text.push(next),
+                Some(next) => {
+                    text.push(ch);
+                    text.push(next);
+                }
+                None => break,
+            },
+            c if c == quote => return Ok(text),
+            c => text.push(c),
+        }
+    }
+    Err(DcsvError::InvalidQuery(format!(
+        "Missing closing quote {}",
+        quote
+    )))
+}
+
+/// Recursive descent parser of query tokens
+struct QueryParser {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.cursor)
+    }
+
+    fn next(&mut self) -> DcsvResult<Token> {
+        let token = self
+            .tokens
+            .get(self.cursor)
+            .cloned()
+            .ok_or_else(|| DcsvError::InvalidQuery("Unexpected end of query".to_string()))?;
+        self.cursor += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> DcsvResult<()> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(DcsvError::InvalidQuery(format!(
+                "Expected \"{}\" but got \"{}\"",
+                expected, token
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> DcsvResult<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.cursor += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> DcsvResult<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.cursor += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> DcsvResult<Expr> {
+        match self.next()? {
+            Token::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Column(column) => self.parse_condition(column),
+            token => Err(DcsvError::InvalidQuery(format!(
+                "Expected a column but got \"{}\"",
+                token
+            ))),
+        }
+    }
+
+    fn parse_condition(&mut self, column: String) -> DcsvResult<Expr> {
+        match self.next()? {
+            Token::Operator(operator) => Ok(Expr::Compare {
+                column,
+                operator,
+                literal: self.parse_literal()?,
+            }),
+            token @ (Token::Match | Token::NotMatch) => {
+                let pattern = match self.next()? {
+                    Token::Regex(pattern) | Token::Text(pattern) => pattern,
+                    token => {
+                        return Err(DcsvError::InvalidQuery(format!(
+                            "Expected a pattern but got \"{}\"",
+                            token
+                        )))
+                    }
+                };
+                let pattern = Regex::new(&pattern).map_err(|err| {
+                    DcsvError::InvalidQuery(format!("Invalid pattern \"{}\" : {}", pattern, err))
+                })?;
+                let expr = Expr::Match { column, pattern };
+                if token == Token::NotMatch {
+                    Ok(Expr::Not(Box::new(expr)))
+                } else {
+                    Ok(expr)
+                }
+            }
+            Token::In => {
+                self.expect(Token::LParen)?;
+                let mut literals = vec![self.parse_literal()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.cursor += 1;
+                    literals.push(self.parse_literal()?);
+                }
+                self.expect(Token::RParen)?;
+                Ok(Expr::In { column, literals })
+            }
+            token => Err(DcsvError::InvalidQuery(format!(
+                "Expected an operator after \"{}\" but got \"{}\"",
+                column, token
+            ))),
+        }
+    }
+
+    fn parse_literal(&mut self) -> DcsvResult<Literal> {
+        match self.next()? {
+            Token::Number(num) => Ok(Literal::Number(num)),
+            Token::Text(text) => Ok(Literal::Text(text)),
+            token => Err(DcsvError::InvalidQuery(format!(
+                "Expected a literal but got \"{}\"",
+                token
+            ))),
+        }
+    }
+}
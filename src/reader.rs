@@ -3,12 +3,14 @@
 //! You can also configure reader with multiple builder methods
 
 use crate::error::{DcsvError, DcsvResult};
+use crate::meta::Meta;
 use crate::parser::Parser;
 use crate::utils::ALPHABET;
 use crate::value::Value;
 use crate::virtual_data::VirtualData;
 use crate::{Column, VCont, VirtualArray};
 use std::io::BufRead;
+use unicode_width::UnicodeWidthStr;
 
 /// Csv Reader
 ///
@@ -226,6 +228,7 @@ impl Reader {
                 .expect("Failed to read until");
         }
 
+        data.mark_clean();
         Ok(data)
     }
 
@@ -284,15 +287,12 @@ impl Reader {
                             )));
                         }
                         let header = std::mem::take(&mut self.option.custom_header);
-                        data.columns = header.iter().map(|h| Column::empty(h)).collect::<Vec<_>>();
+                        set_array_header(&mut data, header.iter().map(|h| h.as_str()));
                     } else if self.option.read_header {
                         if self.option.trim {
-                            data.columns = row
-                                .iter()
-                                .map(|s| Column::empty(s.trim()))
-                                .collect::<Vec<_>>();
+                            set_array_header(&mut data, row.iter().map(|s| s.trim()));
                         } else {
-                            data.columns = row.iter().map(|h| Column::empty(h)).collect::<Vec<_>>();
+                            set_array_header(&mut data, row.iter().map(|h| h.as_str()));
                         }
                         row_count += 1;
                         num_bytes = csv_stream
@@ -301,10 +301,10 @@ impl Reader {
                         continue;
                     } else {
                         // Create a header
-                        data.columns = make_arbitrary_column(row.len())
-                            .iter()
-                            .map(|h| Column::empty(h))
-                            .collect::<Vec<_>>();
+                        set_array_header(
+                            &mut data,
+                            make_arbitrary_column(row.len()).iter().map(|h| h.as_str()),
+                        );
                     }
                 }
 
@@ -335,6 +335,7 @@ impl Reader {
                 .expect("Failed to read until");
         }
 
+        data.mark_clean();
         Ok(data)
     }
 }
@@ -368,6 +369,19 @@ fn add_array_row(data: &mut VirtualArray, mut row: Vec<String>) -> DcsvResult<()
     Ok(())
 }
 
+/// Set columns and metas of a virtual array with given names
+fn set_array_header<'a>(data: &mut VirtualArray, names: impl Iterator<Item = &'a str>) {
+    let (columns, metas) = names
+        .map(|name| {
+            let mut meta = Meta::new();
+            meta.set_width(UnicodeWidthStr::width(name));
+            (Column::empty(name), meta)
+        })
+        .unzip();
+    data.columns = columns;
+    data.metas = metas;
+}
+
 /// Add multiple columns with given names
 fn add_multiple_columns(data: &mut VirtualData, column_names: &[String]) -> DcsvResult<()> {
     for (idx, col) in column_names.iter().enumerate() {
@@ -377,11 +391,10 @@ fn add_multiple_columns(data: &mut VirtualData, column_names: &[String]) -> Dcsv
 }
 
 /// Create arbitrary column names
-fn make_arbitrary_column(size: usize) -> Vec<String> {
+pub(crate) fn make_arbitrary_column(size: usize) -> Vec<String> {
     let mut column_names: Vec<String> = vec![];
     for index in 0..size {
-        let index = index + 1;
-        let target = ALPHABET[index % ALPHABET.len() - 1];
+        let target = ALPHABET[index % ALPHABET.len()];
         let name = target.repeat(index / ALPHABET.len() + 1);
         column_names.push(name);
     }
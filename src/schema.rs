@@ -0,0 +1,477 @@
+//! Schema is a set of column definitions
+//!
+//! Schema can be exported from virtual data, inferred from values of virtual data and applied to
+//! virtual data.
+
+use crate::constraint::{KeyConstraint, KeyKind};
+use crate::error::{DcsvError, DcsvResult};
+use crate::reader::Reader;
+use crate::utils::escape_field;
+use crate::validation::{LimiterMode, ValidationReport};
+use crate::validator::ValidatorRegistry;
+use crate::value::{Value, ValueLimiter, ValueType};
+use crate::vcont::VCont;
+use crate::virtual_data::{Column, VirtualData};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Header for csv schema
+pub const SCHEMA_HEADER: &str = "column,type,default,variant,pattern";
+
+/// Header for csv schema with validator and required flag
+///
+/// Schema is displayed with this header. Csv schema with SCHEMA_HEADER is still readable.
+pub const EXTENDED_SCHEMA_HEADER: &str = "column,type,default,variant,pattern,validator,required";
+
+/// Column definitions of virtual data
+///
+/// Schema is displayed as csv value with EXTENDED_SCHEMA_HEADER. Csv form only carries columns, while
+/// keys and missing values are carried by table schema form.
+///
+/// Missing values are tokens which are treated as empty values. Default is an empty string.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+    pub primary_key: Vec<String>,
+    pub unique_keys: Vec<Vec<String>>,
+    pub missing_values: Vec<String>,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+impl Schema {
+    /// Create a new schema with columns
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            primary_key: vec![],
+            unique_keys: vec![],
+            missing_values: vec![String::new()],
+        }
+    }
+
+    /// Get a column by name
+    pub fn get_column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Read a schema from csv form
+    ///
+    /// First line is a header and each following line is a column definition. Validator names
+    /// are resolved from a given registry. Csv form doesn't carry keys and missing values.
+    pub fn from_csv(src: &str, registry: &ValidatorRegistry) -> DcsvResult<Self> {
+        let data = Reader::new()
+            .ignore_empty_row(true)
+            .consume_dquote(true)
+            .data_from_stream(src.as_bytes())?;
+        let mut columns = vec![];
+        for row in 0..data.get_row_count() {
+            let line = (0..data.get_column_count())
+                .map(|col| {
+                    data.get_cell(row, col)
+                        .map(|v| v.to_string())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>();
+            let name = line.first().ok_or_else(|| {
+                DcsvError::InvalidSchema("Schema line doesn't have a column name".to_string())
+            })?;
+            let limiter = ValueLimiter::from_line_with_registry(&line[1..], registry)?;
+            columns.push(Column::new(name, limiter.get_type(), Some(limiter)));
+        }
+        Ok(Self::new(columns))
+    }
+}
+
+impl From<&VirtualData> for Schema {
+    fn from(data: &VirtualData) -> Self {
+        let mut schema = Self::new(data.columns.clone());
+        for constraint in data.get_key_constraints() {
+            match constraint.get_kind() {
+                KeyKind::PrimaryKey => schema.primary_key = constraint.get_columns().to_vec(),
+                KeyKind::Unique => schema.unique_keys.push(constraint.get_columns().to_vec()),
+            }
+        }
+        schema
+    }
+}
+
+/// Csv form of a schema
+///
+/// Fields which have a comma, a double quote or a line break are quoted. Each line is structured
+/// with following order.
+///
+/// - column
+/// - type
+/// - default
+/// - variant
+/// - pattern
+/// - validator
+/// - required
+impl std::fmt::Display for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut schema = format!("{}\n", EXTENDED_SCHEMA_HEADER);
+        for col in &self.columns {
+            let limiter = &col.limiter;
+            let line = [
+                col.name.clone(),
+                limiter.get_type().to_string(),
+                limiter
+                    .get_default()
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                limiter
+                    .get_variant()
+                    .map(|s| s.iter().map(|s| s.to_string()).collect::<Vec<String>>())
+                    .unwrap_or_default()
+                    .join(" "),
+                limiter
+                    .get_pattern()
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                limiter
+                    .get_validator()
+                    .map(|s| s.get_name())
+                    .unwrap_or_default()
+                    .to_string(),
+                limiter.is_required().to_string(),
+            ]
+            .iter()
+            .map(|field| escape_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+
+            schema.push_str(&(line + "\n"));
+        }
+        write!(f, "{}", schema)
+    }
+}
+
+/// Options for schema inference
+///
+/// # Usage
+///
+/// ```rust
+/// use dcsv::InferOption;
+///
+/// let option = InferOption::new()
+///     .max_variants(5)
+///     .infer_pattern(true)
+///     .infer_required(true);
+/// ```
+#[derive(Clone, Debug)]
+pub struct InferOption {
+    pub max_variants: usize,
+    pub infer_pattern: bool,
+    pub infer_required: bool,
+}
+
+impl Default for InferOption {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InferOption {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            max_variants: 10,
+            infer_pattern: false,
+            infer_required: false,
+        }
+    }
+
+    /// Maximum count of distinct values to be inferred as variants
+    ///
+    /// Zero disables variant inference.
+    pub fn max_variants(mut self, count: usize) -> Self {
+        self.max_variants = count;
+        self
+    }
+
+    /// Infer regex pattern when every value shares a same shape
+    pub fn infer_pattern(mut self, tv: bool) -> Self {
+        self.infer_pattern = tv;
+        self
+    }
+
+    /// Infer required when column has no empty value
+    pub fn infer_required(mut self, tv: bool) -> Self {
+        self.infer_required = tv;
+        self
+    }
+}
+
+impl VirtualData {
+    /// Get schema of virtual data
+    pub fn get_schema(&self) -> Schema {
+        Schema::from(self)
+    }
+
+    /// Export virtual data's schema(limiter) as string form
+    ///
+    /// Schema is expressed as csv value. Each line is structured with following order.
+    ///
+    /// - column
+    /// - type
+    /// - default
+    /// - variant
+    /// - pattern
+    /// - validator
+    /// - required
+    pub fn export_schema(&self) -> String {
+        self.get_schema().to_string()
+    }
+
+    /// Infer a schema from values of virtual data
+    ///
+    /// Each column gets a proposed limiter.
+    ///
+    /// - Type is number when every non empty value is a number.
+    /// - Column with few distinct values gets variants and the most frequent value as default.
+    /// - Column with values of a same shape gets a pattern when option allows.
+    /// - Column without empty values is required when option allows.
+    ///
+    /// Values with whitespaces or empty values cannot be expressed as variants, thus such column
+    /// doesn't get variants.
+    pub fn infer_schema(&self, option: &InferOption) -> Schema {
+        let mut columns = vec![];
+        for (index, col) in self.columns.iter().enumerate() {
+            // It is safe to unwrap because index is from columns
+            let values = self
+                .get_column_iterator(index)
+                .unwrap()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>();
+            let limiter = infer_limiter(&values, option);
+            columns.push(Column::new(&col.name, limiter.get_type(), Some(limiter)));
+        }
+        Schema::new(columns)
+    }
+
+    /// Apply a schema to columns with same names
+    ///
+    /// Columns which don't exist in schema are left untouched. Values which are missing value
+    /// tokens are replaced with empty values before limiters are applied. Key constraints of a
+    /// schema are added after limiters.
+    ///
+    /// Violations of every column and key are merged into a single report. Key constraint which
+    /// existing rows violate is not added unless mode is strict, in which case this fails.
+    /// Every column and key is validated before any change, thus strict mode doesn't modify data
+    /// when it fails.
+    pub fn apply_schema(
+        &mut self,
+        schema: &Schema,
+        mode: LimiterMode,
+    ) -> DcsvResult<ValidationReport> {
+        let mut report = ValidationReport::new();
+        let tokens = schema
+            .missing_values
+            .iter()
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+
+        // Every column is conformed before any of them is applied
+        let mut conversions = vec![];
+        let mut limiters = vec![];
+        for col in &schema.columns {
+            if let Some(index) = self.columns.iter().position(|c| c.name == col.name) {
+                let (updates, applied) =
+                    self.conform_column_with_missing(index, &col.limiter, mode, &tokens)?;
+                report.violations.extend(applied.violations);
+                conversions.push((col.name.clone(), updates));
+                limiters.push((index, col));
+            }
+        }
+
+        let mut keys = schema
+            .unique_keys
+            .iter()
+            .map(|k| (KeyKind::Unique, k))
+            .collect::<Vec<_>>();
+        if !schema.primary_key.is_empty() {
+            keys.insert(0, (KeyKind::PrimaryKey, &schema.primary_key));
+        }
+        keys.retain(|(kind, columns)| {
+            let exists = self
+                .get_key_constraints()
+                .iter()
+                .any(|c| c.get_kind() == *kind && c.get_columns() == columns.as_slice());
+            // Key over columns which data doesn't have is not applicable
+            let applicable = columns
+                .iter()
+                .all(|c| self.columns.iter().any(|col| col.name == *c));
+            !exists && applicable
+        });
+        // Strict mode fails when converted values violate a new key
+        let added = if mode == LimiterMode::Strict {
+            keys.iter()
+                .map(|(kind, columns)| KeyConstraint::new(*kind, columns.to_vec()))
+                .collect::<Vec<_>>()
+        } else {
+            vec![]
+        };
+        self.check_converted_keys(&conversions, &[], &added)?;
+
+        // Changes are validated above, thus following operations don't fail in strict mode
+        for ((index, col), (_, updates)) in limiters.into_iter().zip(conversions) {
+            self.apply_conformed(index, &col.limiter, updates);
+            let column = &mut self.columns[index];
+            column.description = col.description.clone();
+            column.unit = col.unit.clone();
+        }
+        for (kind, columns) in keys {
+            let added = match kind {
+                KeyKind::PrimaryKey => self.set_primary_key(columns),
+                KeyKind::Unique => self.add_unique_constraint(columns),
+            };
+            if let Err(err) = added {
+                if mode == LimiterMode::Strict {
+                    return Err(err);
+                }
+                let constraint = KeyConstraint::new(kind, columns.clone());
+                report
+                    .violations
+                    .extend(self.get_key_violations(&constraint));
+            }
+        }
+        report.violations.sort_by_key(|v| v.row);
+        Ok(report)
+    }
+}
+
+/// Infer a limiter from string values of a column
+fn infer_limiter(values: &[String], option: &InferOption) -> ValueLimiter {
+    let mut limiter = ValueLimiter::default();
+    let non_empty = values.iter().filter(|v| !v.is_empty()).collect::<Vec<_>>();
+    let value_type =
+        if !non_empty.is_empty() && non_empty.iter().all(|v| v.parse::<isize>().is_ok()) {
+            ValueType::Number
+        } else {
+            ValueType::Text
+        };
+    limiter.set_type(value_type);
+
+    if option.infer_required && !values.is_empty() && non_empty.len() == values.len() {
+        limiter.set_required(true);
+    }
+
+    // Distinct values in first seen order with frequencies
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut distinct = vec![];
+    for value in values {
+        let count = counts.entry(value.as_str()).or_insert(0);
+        if *count == 0 {
+            distinct.push(value.as_str());
+        }
+        *count += 1;
+    }
+    // Ties are broken by first seen order
+    let most_frequent = distinct
+        .iter()
+        .rev()
+        .max_by_key(|v| counts[*v])
+        .map(|v| Value::from_str(v, value_type));
+
+    let has_variants = distinct.len() <= option.max_variants
+        && distinct.len() < values.len()
+        && distinct
+            .iter()
+            .all(|v| !v.is_empty() && !v.contains(char::is_whitespace));
+    if has_variants {
+        let variants = distinct
+            .iter()
+            .map(|v| Value::from_str(v, value_type))
+            .collect::<DcsvResult<Vec<_>>>();
+        if let (Ok(variants), Some(Ok(default))) = (variants, most_frequent) {
+            // Default is one of distinct values, thus always among variants
+            limiter.set_variant(default, &variants).ok();
+        }
+        return limiter;
+    }
+
+    if option.infer_pattern && !non_empty.is_empty() && non_empty.len() == values.len() {
+        if let Some(pattern) = infer_pattern(values) {
+            if let Some(Ok(default)) = most_frequent {
+                // Pattern is built from values, thus default always matches
+                limiter.set_pattern(default, pattern).ok();
+            }
+        }
+    }
+    limiter
+}
+
+/// Class of a character for pattern inference
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Digit,
+    Upper,
+    Lower,
+    Literal(char),
+}
+
+/// Infer a regex pattern which every value matches
+///
+/// Values should share a same sequence of character classes, while lengths of each class can
+/// differ.
+fn infer_pattern(values: &[String]) -> Option<Regex> {
+    let mut shape: Option<Vec<(CharClass, usize, usize)>> = None;
+    for value in values {
+        let mut runs: Vec<(CharClass, usize)> = vec![];
+        for ch in value.chars() {
+            let class = if ch.is_ascii_digit() {
+                CharClass::Digit
+            } else if ch.is_ascii_uppercase() {
+                CharClass::Upper
+            } else if ch.is_ascii_lowercase() {
+                CharClass::Lower
+            } else {
+                CharClass::Literal(ch)
+            };
+            match runs.last_mut() {
+                Some((last, len)) if *last == class => *len += 1,
+                _ => runs.push((class, 1)),
+            }
+        }
+
+        match shape.as_mut() {
+            None => shape = Some(runs.iter().map(|(c, l)| (*c, *l, *l)).collect()),
+            Some(shape) => {
+                if shape.len() != runs.len()
+                    || shape.iter().zip(runs.iter()).any(|(s, r)| s.0 != r.0)
+                {
+                    return None;
+                }
+                for (s, r) in shape.iter_mut().zip(runs.iter()) {
+                    s.1 = s.1.min(r.1);
+                    s.2 = s.2.max(r.1);
+                }
+            }
+        }
+    }
+
+    let mut pattern = String::from("^");
+    for (class, min, max) in shape? {
+        let class = match class {
+            CharClass::Digit => "[0-9]".to_string(),
+            CharClass::Upper => "[A-Z]".to_string(),
+            CharClass::Lower => "[a-z]".to_string(),
+            CharClass::Literal(ch) => regex::escape(&ch.to_string()),
+        };
+        pattern.push_str(&class);
+        if min == max {
+            if min > 1 {
+                pattern.push_str(&format!("{{{}}}", min));
+            }
+        } else {
+            pattern.push_str(&format!("{{{},{}}}", min, max));
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).ok()
+}
@@ -0,0 +1,187 @@
+//! Find and replace
+//!
+//! Cells are searched with a regex pattern against their text forms. Scope confines searched
+//! cells and cells are visited row by row.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::validation::{LimiterMode, ValidationReport, Violation};
+use crate::value::Value;
+use crate::vcont::VCont;
+use crate::virtual_array::VirtualArray;
+use crate::virtual_data::{Row, VirtualData};
+use regex::Regex;
+
+/// Cells to search
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum Scope {
+    /// Every cell
+    #[default]
+    All,
+    /// Every cell of given column indices
+    Columns(Vec<usize>),
+    /// Rectangular range between two corners as (row, column), inclusive
+    Range {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+}
+
+impl Scope {
+    /// Get coordinates of cells in scope
+    pub(crate) fn cells(
+        &self,
+        row_count: usize,
+        column_count: usize,
+    ) -> DcsvResult<Vec<(usize, usize)>> {
+        let cells = match self {
+            Self::All => (0..row_count)
+                .flat_map(|row| (0..column_count).map(move |column| (row, column)))
+                .collect(),
+            Self::Columns(columns) => {
+                if columns.iter().any(|c| *c >= column_count) {
+                    return Err(DcsvError::OutOfRangeError);
+                }
+                (0..row_count)
+                    .flat_map(|row| columns.iter().map(move |column| (row, *column)))
+                    .collect()
+            }
+            Self::Range { from, to } => {
+                let rows = from.0.min(to.0)..=from.0.max(to.0);
+                let columns = from.1.min(to.1)..=from.1.max(to.1);
+                if *rows.end() >= row_count || *columns.end() >= column_count {
+                    return Err(DcsvError::OutOfRangeError);
+                }
+                rows.flat_map(|row| columns.clone().map(move |column| (row, column)))
+                    .collect()
+            }
+        };
+        Ok(cells)
+    }
+}
+
+/// Compile a search pattern
+pub(crate) fn compile(pattern: &str) -> DcsvResult<Regex> {
+    Regex::new(pattern).map_err(|err| {
+        DcsvError::InvalidQuery(format!("Invalid pattern \"{}\" : {}", pattern, err))
+    })
+}
+
+/// Result of replacement
+///
+/// Row of a violation is a row index of the cell.
+#[derive(Clone, Debug, Default)]
+pub struct ReplaceReport {
+    pub replacements: usize,
+    pub violations: ValidationReport,
+}
+
+impl VirtualData {
+    /// Replace matches of a pattern in cells and return a report
+    ///
+    /// Replacement can refer to capture groups such as "$1" or "${name}". Replaced text is
+    /// converted into a column type and unqualified values are treated by mode.
+    ///
+    /// - Strict    : Fail without modifying data
+    /// - Overwrite : Set limiter's default value
+    /// - Collect   : Keep an original value
+    ///
+    /// Derived columns are skipped. Key constraints, row validators and enforced foreign keys
+    /// are checked in every mode and data is not modified when they fail.
+    pub fn replace(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        scope: &Scope,
+        mode: LimiterMode,
+    ) -> DcsvResult<ReplaceReport> {
+        let regex = compile(pattern)?;
+        let mut report = ReplaceReport::default();
+        // Candidate rows are collected first, thus checks see a final state
+        let mut candidates: Vec<(usize, Row, Vec<String>)> = vec![];
+        for (row, column) in scope.cells(self.get_row_count(), self.get_column_count())? {
+            let col = &self.columns[column];
+            if self.is_derived_column(&col.name) {
+                continue;
+            }
+            // It is safe to unwrap because scope only has valid cells
+            let text = self.get_cell(row, column).unwrap().to_string();
+            let count = regex.find_iter(&text).count();
+            if count == 0 {
+                continue;
+            }
+            let replaced = Value::Text(regex.replace_all(&text, replacement).into_owned());
+            let value = match col.limiter.conform(&replaced) {
+                Ok(value) => value,
+                Err(rule) => {
+                    if mode == LimiterMode::Strict {
+                        return Err(DcsvError::InvalidCellData(format!(
+                            "Replaced value \"{}\" of cell {},{} doesn't match limiter's qualification : {}",
+                            replaced, row, column, rule
+                        )));
+                    }
+                    report.violations.push(Violation {
+                        row,
+                        columns: vec![col.name.clone()],
+                        values: vec![replaced],
+                        rule,
+                    });
+                    if mode == LimiterMode::Collect {
+                        continue;
+                    }
+                    col.limiter.get_default_or_empty()
+                }
+            };
+            // Cells are visited row by row
+            match candidates.last_mut() {
+                Some((index, candidate, changed)) if *index == row => {
+                    candidate.update_cell_value(&col.name, value);
+                    changed.push(col.name.clone());
+                }
+                _ => {
+                    let mut candidate = self.rows[row].clone();
+                    candidate.update_cell_value(&col.name, value);
+                    candidates.push((row, candidate, vec![col.name.clone()]));
+                }
+            }
+            report.replacements += count;
+        }
+        self.replace_rows(candidates)?;
+        Ok(report)
+    }
+}
+
+impl VirtualArray {
+    /// Replace matches of a pattern in cells and return a count of replacements
+    ///
+    /// Replacement can refer to capture groups such as "$1" or "${name}". Number stays a number
+    /// when replaced text is a number. Formula cells are skipped.
+    pub fn replace(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        scope: &Scope,
+    ) -> DcsvResult<usize> {
+        let regex = compile(pattern)?;
+        let formulas = self.get_formula_cells();
+        let mut replacements = 0;
+        for (row, column) in scope.cells(self.get_row_count(), self.get_column_count())? {
+            if formulas.binary_search(&(row, column)).is_ok() {
+                continue;
+            }
+            let text = self.rows[row][column].to_string();
+            let count = regex.find_iter(&text).count();
+            if count == 0 {
+                continue;
+            }
+            let replaced = regex.replace_all(&text, replacement).into_owned();
+            // Keep a number when replaced text is still a number
+            let value = match (&self.rows[row][column], replaced.parse::<isize>()) {
+                (Value::Number(_), Ok(num)) => Value::Number(num),
+                _ => Value::Text(replaced),
+            };
+            self.set_cell(row, column, value)?;
+            replacements += count;
+        }
+        Ok(replacements)
+    }
+}
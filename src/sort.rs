@@ -0,0 +1,157 @@
+//! Sort keys for rows
+//!
+//! Rows are sorted by sort keys in given order. Later key is only compared when former keys are
+//! equal and rows with equal keys keep their original order.
+
+use crate::comparator::Comparator;
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::Value;
+use crate::vcont::VCont;
+use std::cmp::Ordering;
+
+/// Direction of a sort key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Position of empty values
+///
+/// Position of empty values doesn't depend on sort order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NullOrder {
+    First,
+    #[default]
+    Last,
+}
+
+/// Sort key of a single column
+///
+/// # Usage
+///
+/// ```rust
+/// use dcsv::{Comparator, NullOrder, SortKey};
+///
+/// let keys = [
+///     SortKey::asc(0).comparator(Comparator::Natural),
+///     SortKey::desc(2).nulls(NullOrder::First),
+/// ];
+/// ```
+#[derive(Clone, Debug)]
+pub struct SortKey {
+    pub column: usize,
+    pub order: SortOrder,
+    pub nulls: NullOrder,
+    pub comparator: Comparator,
+}
+
+impl SortKey {
+    /// Create a sort key with order
+    pub fn new(column: usize, order: SortOrder) -> Self {
+        Self {
+            column,
+            order,
+            nulls: NullOrder::default(),
+            comparator: Comparator::default(),
+        }
+    }
+
+    /// Ascending sort key
+    pub fn asc(column: usize) -> Self {
+        Self::new(column, SortOrder::Ascending)
+    }
+
+    /// Descending sort key
+    pub fn desc(column: usize) -> Self {
+        Self::new(column, SortOrder::Descending)
+    }
+
+    /// Set position of empty values
+    pub fn nulls(mut self, nulls: NullOrder) -> Self {
+        self.nulls = nulls;
+        self
+    }
+
+    /// Set comparator of values
+    pub fn comparator(mut self, comparator: Comparator) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Compare two values by sort key
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        match (a.is_empty(), b.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => match self.nulls {
+                NullOrder::First => Ordering::Less,
+                NullOrder::Last => Ordering::Greater,
+            },
+            (false, true) => match self.nulls {
+                NullOrder::First => Ordering::Greater,
+                NullOrder::Last => Ordering::Less,
+            },
+            (false, false) => {
+                let ordering = self.comparator.compare(a, b);
+                match self.order {
+                    SortOrder::Ascending => ordering,
+                    SortOrder::Descending => ordering.reverse(),
+                }
+            }
+        }
+    }
+}
+
+/// Get row indices in sorted order
+///
+/// Each item of values is a row's values of sort keys in same order with keys. Sort is stable.
+pub(crate) fn sorted_indices(keys: &[SortKey], values: &[Vec<&Value>]) -> Vec<usize> {
+    let mut indices = (0..values.len()).collect::<Vec<_>>();
+    indices.sort_by(|&a, &b| {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| key.compare(values[a][i], values[b][i]))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+    indices
+}
+
+/// Get row indices of a container in sorted order
+///
+/// This fails when any key column is out of range.
+pub(crate) fn row_order(data: &(impl VCont + ?Sized), keys: &[SortKey]) -> DcsvResult<Vec<usize>> {
+    if keys.iter().any(|k| k.column >= data.get_column_count()) {
+        return Err(DcsvError::OutOfRangeError);
+    }
+    let empty = Value::default();
+    let values = (0..data.get_row_count())
+        .map(|row| {
+            keys.iter()
+                .map(|k| data.get_cell(row, k.column).unwrap_or(&empty))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    Ok(sorted_indices(keys, &values))
+}
+
+/// Get positions of items after reordering with sorted indices
+///
+/// Reordering with positions reverts reordering with indices.
+pub(crate) fn inverse(indices: &[usize]) -> Vec<usize> {
+    let mut positions = vec![0; indices.len()];
+    for (position, index) in indices.iter().enumerate() {
+        positions[*index] = position;
+    }
+    positions
+}
+
+/// Reorder items with sorted indices
+pub(crate) fn permute<T>(items: Vec<T>, indices: &[usize]) -> Vec<T> {
+    let mut items = items.into_iter().map(Some).collect::<Vec<_>>();
+    indices
+        .iter()
+        .filter_map(|&index| items[index].take())
+        .collect()
+}
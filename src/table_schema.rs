@@ -0,0 +1,274 @@
+//! Frictionless table schema
+//!
+//! Table schema is a json form of column definitions from Frictionless Data specification. Table
+//! schema is mapped onto columns and value limiters.
+//!
+//! - "integer" type is mapped to number and every other type is mapped to text
+//! - "required", "unique", "enum" and "pattern" constraints are supported while other
+//!   constraints such as "minimum" and "maxLength" are refused with an error
+//! - "primaryKey", "uniqueKeys" and "missingValues" are supported while "foreignKeys" is ignored
+//! - "unit" and "validator" are dcsv specific field properties
+//! - Missing values are replaced with empty values and are still checked by limiters
+//!
+//! Table schema doesn't have a default value. First value of enum is a default value, thus
+//! default value is exported as a first value of enum.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::schema::Schema;
+use crate::validator::ValidatorRegistry;
+use crate::value::{Value, ValueLimiter, ValueType};
+use crate::virtual_data::{Column, VirtualData};
+use regex::Regex;
+use serde_json::{json, Map, Value as Json};
+
+/// Table schema constraints which are mapped onto limiters and keys
+const SUPPORTED_CONSTRAINTS: [&str; 4] = ["required", "unique", "enum", "pattern"];
+
+impl Schema {
+    /// Export schema as frictionless table schema json
+    pub fn to_table_schema(&self) -> String {
+        let mut fields = vec![];
+        for col in &self.columns {
+            let limiter = &col.limiter;
+            let mut field = Map::new();
+            field.insert("name".to_string(), json!(col.name));
+            field.insert(
+                "type".to_string(),
+                json!(match limiter.get_type() {
+                    ValueType::Number => "integer",
+                    ValueType::Text => "string",
+                }),
+            );
+            if let Some(description) = &col.description {
+                field.insert("description".to_string(), json!(description));
+            }
+            if let Some(unit) = &col.unit {
+                field.insert("unit".to_string(), json!(unit));
+            }
+
+            let mut constraints = Map::new();
+            if limiter.is_required() {
+                constraints.insert("required".to_string(), json!(true));
+            }
+            if self
+                .unique_keys
+                .iter()
+                .any(|k| k.len() == 1 && k[0] == col.name)
+            {
+                constraints.insert("unique".to_string(), json!(true));
+            }
+            if let Some(variant) = limiter.get_variant() {
+                // Default value comes first because first value is a default value
+                let mut values = vec![];
+                if let Some(default) = limiter.get_default() {
+                    values.push(default);
+                }
+                values.extend(variant.iter().filter(|v| Some(*v) != limiter.get_default()));
+                constraints.insert(
+                    "enum".to_string(),
+                    Json::Array(values.into_iter().map(value_to_json).collect()),
+                );
+            } else if let Some(pattern) = limiter.get_pattern() {
+                constraints.insert("pattern".to_string(), json!(anchored(pattern.as_str())));
+            }
+            if !constraints.is_empty() {
+                field.insert("constraints".to_string(), Json::Object(constraints));
+            }
+
+            if let Some(validator) = limiter.get_validator() {
+                field.insert("validator".to_string(), json!(validator.get_name()));
+            }
+            fields.push(Json::Object(field));
+        }
+
+        let mut schema = Map::new();
+        schema.insert("fields".to_string(), Json::Array(fields));
+        schema.insert("missingValues".to_string(), json!(self.missing_values));
+        if !self.primary_key.is_empty() {
+            schema.insert("primaryKey".to_string(), json!(self.primary_key));
+        }
+        let unique_keys = self
+            .unique_keys
+            .iter()
+            .filter(|k| k.len() > 1)
+            .collect::<Vec<_>>();
+        if !unique_keys.is_empty() {
+            schema.insert("uniqueKeys".to_string(), json!(unique_keys));
+        }
+
+        // Serializing a json value doesn't fail
+        serde_json::to_string_pretty(&Json::Object(schema)).unwrap()
+    }
+
+    /// Import schema from frictionless table schema json
+    ///
+    /// Validator names are resolved from a given registry. This fails when a field has an
+    /// unsupported constraint.
+    pub fn from_table_schema(src: &str, registry: &ValidatorRegistry) -> DcsvResult<Self> {
+        let root: Json = serde_json::from_str(src)
+            .map_err(|err| DcsvError::InvalidSchema(format!("Invalid json : {}", err)))?;
+        let fields = root
+            .get("fields")
+            .and_then(|f| f.as_array())
+            .ok_or_else(|| DcsvError::InvalidSchema("Schema needs fields array".to_string()))?;
+
+        let mut schema = Schema::default();
+        for field in fields {
+            let name = get_str(field, "name")?
+                .ok_or_else(|| DcsvError::InvalidSchema("Field needs a name".to_string()))?;
+            let value_type = match get_str(field, "type")? {
+                Some("integer") => ValueType::Number,
+                _ => ValueType::Text,
+            };
+            let mut limiter = ValueLimiter::default();
+            limiter.set_type(value_type);
+
+            if let Some(constraints) = field.get("constraints") {
+                // Refuse constraints which a limiter can't express rather than dropping them
+                if let Some(key) = constraints.as_object().and_then(|c| {
+                    c.keys()
+                        .find(|k| !SUPPORTED_CONSTRAINTS.contains(&k.as_str()))
+                }) {
+                    return Err(DcsvError::InvalidSchema(format!(
+                        "Constraint \"{}\" of \"{}\" is not supported",
+                        key, name
+                    )));
+                }
+                if get_bool(constraints, "required")? {
+                    limiter.set_required(true);
+                }
+                if get_bool(constraints, "unique")? {
+                    schema.unique_keys.push(vec![name.to_string()]);
+                }
+                if let Some(values) = constraints.get("enum") {
+                    let values = values
+                        .as_array()
+                        .ok_or_else(|| {
+                            DcsvError::InvalidSchema(format!(
+                                "Enum of \"{}\" is not an array",
+                                name
+                            ))
+                        })?
+                        .iter()
+                        .map(|v| json_to_value(v, value_type))
+                        .collect::<DcsvResult<Vec<_>>>()?;
+                    if let Some(default) = values.first() {
+                        limiter.set_variant(default.clone(), &values)?;
+                    }
+                } else if let Some(pattern) = get_str(constraints, "pattern")? {
+                    // Table schema's pattern should match a whole value
+                    let pattern = Regex::new(&format!("^(?:{})$", pattern)).map_err(|err| {
+                        DcsvError::InvalidSchema(format!(
+                            "Invalid pattern of \"{}\" : {}",
+                            name, err
+                        ))
+                    })?;
+                    limiter.set_pattern_without_default(pattern);
+                }
+            }
+
+            if let Some(validator) = get_str(field, "validator")? {
+                limiter.set_validator(registry.try_get(validator)?.clone());
+            }
+
+            let mut column = Column::new(name, value_type, Some(limiter));
+            column.description = get_str(field, "description")?.map(|s| s.to_string());
+            column.unit = get_str(field, "unit")?.map(|s| s.to_string());
+            schema.columns.push(column);
+        }
+
+        if let Some(tokens) = root.get("missingValues") {
+            schema.missing_values = get_str_array(tokens, "missingValues")?;
+        }
+        if let Some(key) = root.get("primaryKey") {
+            schema.primary_key = match key {
+                Json::String(column) => vec![column.to_string()],
+                _ => get_str_array(key, "primaryKey")?,
+            };
+        }
+        if let Some(keys) = root.get("uniqueKeys") {
+            let keys = keys.as_array().ok_or_else(|| {
+                DcsvError::InvalidSchema("uniqueKeys is not an array".to_string())
+            })?;
+            for key in keys {
+                schema.unique_keys.push(get_str_array(key, "uniqueKeys")?);
+            }
+        }
+        Ok(schema)
+    }
+}
+
+impl VirtualData {
+    /// Export virtual data's schema as frictionless table schema json
+    pub fn export_table_schema(&self) -> String {
+        self.get_schema().to_table_schema()
+    }
+}
+
+/// Convert a pattern into a pattern which matches a whole value
+fn anchored(pattern: &str) -> String {
+    // Strip anchors only when it is safe to do so
+    if let Some(inner) = pattern.strip_prefix('^').and_then(|p| p.strip_suffix('$')) {
+        if !inner.contains('|') && !inner.ends_with('\\') {
+            return inner.to_string();
+        }
+    }
+    format!(".*(?:{}).*", pattern)
+}
+
+/// Convert a value into a json value
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Number(num) => json!(num),
+        Value::Text(text) => json!(text),
+    }
+}
+
+/// Convert a json value into a value of given type
+fn json_to_value(json: &Json, value_type: ValueType) -> DcsvResult<Value> {
+    match json {
+        Json::String(text) => Value::from_str(text, value_type),
+        Json::Number(num) => Value::from_str(&num.to_string(), value_type),
+        _ => Err(DcsvError::InvalidSchema(format!(
+            "{} is not a valid enum value",
+            json
+        ))),
+    }
+}
+
+/// Get an optional string property
+fn get_str<'a>(json: &'a Json, key: &str) -> DcsvResult<Option<&'a str>> {
+    match json.get(key) {
+        None => Ok(None),
+        Some(Json::String(text)) => Ok(Some(text)),
+        Some(_) => Err(DcsvError::InvalidSchema(format!(
+            "\"{}\" should be a string",
+            key
+        ))),
+    }
+}
+
+/// Get an optional boolean property which is false by default
+fn get_bool(json: &Json, key: &str) -> DcsvResult<bool> {
+    match json.get(key) {
+        None => Ok(false),
+        Some(Json::Bool(tv)) => Ok(*tv),
+        Some(_) => Err(DcsvError::InvalidSchema(format!(
+            "\"{}\" should be a boolean",
+            key
+        ))),
+    }
+}
+
+/// Get an array of strings
+fn get_str_array(json: &Json, key: &str) -> DcsvResult<Vec<String>> {
+    json.as_array()
+        .and_then(|a| {
+            a.iter()
+                .map(|v| v.as_str().map(|s| s.to_string()))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| {
+            DcsvError::InvalidSchema(format!("\"{}\" should be an array of strings", key))
+        })
+}
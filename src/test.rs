@@ -41,4 +41,1174 @@ mod testos {
 
         Ok(())
     }
+
+    /// Custom validator is evaluated by limiter and resolved from schema by name
+    #[test]
+    fn custom_validator() -> DcsvResult<()> {
+        use crate::{ValidatorRegistry, Value, ValueLimiter};
+        let mut registry = ValidatorRegistry::new();
+        registry.register("even", |value| match value {
+            Value::Number(num) if num % 2 == 0 => Ok(()),
+            _ => Err(format!("\"{}\" is not even", value)),
+        });
+
+        let mut data = Reader::new().data_from_stream("id\n2\n4".as_bytes())?;
+        let limiter =
+            ValueLimiter::from_line_with_registry(&["number", "", "", "", "even"], &registry)?;
+        data.set_limiter(0, &limiter, true)?;
+        assert!(data.set_cell(0, 0, Value::Number(3)).is_err());
+        data.set_cell(0, 0, Value::Number(6))?;
+        assert!(data.export_schema().contains("id,Number,,,,even"));
+        assert!(ValueLimiter::from_line(&["number", "", "", "", "even"]).is_err());
+        Ok(())
+    }
+
+    /// Required column rejects empty values and round trips through schema
+    #[test]
+    fn required_column() -> DcsvResult<()> {
+        use crate::{Value, ValueLimiter};
+        let mut data = Reader::new().data_from_stream("id,name\n1,a\n2,".as_bytes())?;
+        let limiter = ValueLimiter::from_line(&["text", "", "", "", "", "true"])?;
+        assert!(data.set_limiter(1, &limiter, true).is_err());
+        data.set_cell(1, 1, Value::Text("b".to_string()))?;
+        data.set_limiter(1, &limiter, true)?;
+        assert!(data.set_cell(0, 1, Value::Text(String::new())).is_err());
+        assert!(data.export_schema().contains("name,Text,,,,,true"));
+        Ok(())
+    }
+
+    /// Key constraints reject duplicate keys on edits
+    #[test]
+    fn key_constraint() -> DcsvResult<()> {
+        use crate::{Value, ValueLimiter};
+        let mut data =
+            Reader::new().data_from_stream("id,region,name\n1,a,x\n2,a,y\n3,b,z".as_bytes())?;
+        data.set_primary_key(&["id"])?;
+        data.add_unique_constraint(&["region", "name"])?;
+        assert!(data.add_unique_constraint(&["region"]).is_err());
+
+        let text = |s: &str| Value::Text(s.to_string());
+        assert!(data.set_cell(0, 0, text("2")).is_err());
+        assert!(data.set_cell(0, 0, text("")).is_err());
+        data.set_cell(0, 0, text("1"))?;
+        assert!(data
+            .insert_row(3, Some(&[text("3"), text("c"), text("w")]))
+            .is_err());
+        assert!(data
+            .insert_row(3, Some(&[text("4"), text("b"), text("z")]))
+            .is_err());
+        data.insert_row(3, Some(&[text("4"), text("c"), text("z")]))?;
+        assert!(data.set_row(3, &[text("4"), text("a"), text("y")]).is_err());
+        assert!(data.edit_row(3, &[Some(text("1")), None, None]).is_err());
+
+        // Deleted key can be reused
+        data.delete_row(0);
+        data.edit_row(2, &[Some(text("1")), None, None])?;
+
+        // Conversions which make duplicate keys are refused
+        data.set_cell(0, 0, text("01"))?;
+        let limiter = ValueLimiter::from_line(&["number", "0", "", ""])?;
+        assert!(data.set_limiter(0, &limiter, true).is_err());
+        data.apply_all(|v| *v = text(v.to_string().trim_start_matches('0')));
+        assert_eq!(data.get_cell(0, 0), Some(&text("01")));
+        Ok(())
+    }
+
+    /// Row validators are checked on edits and reported by bulk validation
+    #[test]
+    fn row_validator() -> DcsvResult<()> {
+        use crate::Value;
+        let mut data = Reader::new().data_from_stream("start,end\n1,3\n5,4".as_bytes())?;
+        data.add_row_validator("order", &["start", "end"], |values| {
+            if values[0] <= values[1] {
+                Ok(())
+            } else {
+                Err("end is earlier than start".to_string())
+            }
+        })?;
+        let violations = data.validate_rows();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].row, 1);
+        assert_eq!(violations[0].columns, vec!["start", "end"]);
+
+        assert!(data.set_cell(0, 1, Value::Text("0".to_string())).is_err());
+        data.set_cell(1, 0, Value::Text("2".to_string()))?;
+        assert!(data.validate_rows().is_empty());
+
+        // Rejected column value doesn't stay in key indices
+        let text = |s: &str| Value::Text(s.to_string());
+        let mut keyed = Reader::new().data_from_stream("id,state\n1,locked".as_bytes())?;
+        keyed.add_unique_constraint(&["id"])?;
+        keyed.add_row_validator("lock", &["id", "state"], |values| {
+            if values[1].to_string() == "locked" && values[0].to_string() != "1" {
+                Err("locked row cannot change id".to_string())
+            } else {
+                Ok(())
+            }
+        })?;
+        assert!(keyed.set_column(0, text("9")).is_err());
+        keyed.insert_row(1, Some(&[text("9"), text("open")]))?;
+        assert!(keyed
+            .insert_row(2, Some(&[text("1"), text("open")]))
+            .is_err());
+        Ok(())
+    }
+
+    /// Foreign key reports dangling references and refuses enforced edits
+    #[test]
+    fn foreign_key() -> DcsvResult<()> {
+        use crate::{Value, ValueLimiter};
+        let mut customers = Reader::new().data_from_stream("id,name\n1,a\n2,b".as_bytes())?;
+        let mut orders =
+            Reader::new().data_from_stream("order,customer_id\n10,1\n11,3\n12,".as_bytes())?;
+        orders.add_foreign_key("customer", &["customer_id"], &customers, &["id"], true)?;
+
+        let dangling = orders.check_foreign_key("customer", &customers)?;
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].row, 1);
+
+        let text = |s: &str| Value::Text(s.to_string());
+        assert!(orders
+            .insert_row(3, Some(&[text("13"), text("4")]))
+            .is_err());
+        orders.insert_row(3, Some(&[text("13"), text("2")]))?;
+
+        customers.insert_row(2, Some(&[text("4"), text("c")]))?;
+        orders.sync_foreign_key("customer", &customers)?;
+        orders.set_cell(3, 1, text("4"))?;
+
+        // Keys are compared by text form
+        let limiter = ValueLimiter::from_line(&["number", "0", "", ""])?;
+        customers.set_limiter(0, &limiter, true)?;
+        orders.sync_foreign_key("customer", &customers)?;
+        orders.set_cell(3, 1, text("1"))?;
+        assert_eq!(orders.check_foreign_key("customer", &customers)?.len(), 1);
+
+        // Dangling column value doesn't stay in key indices
+        let mut keyed = Reader::new().data_from_stream("order,customer_id\n20,2".as_bytes())?;
+        keyed.add_unique_constraint(&["customer_id"])?;
+        keyed.add_foreign_key("customer", &["customer_id"], &customers, &["id"], true)?;
+        assert!(keyed.set_column(1, text("9")).is_err());
+        customers.insert_row(3, Some(&[Value::Number(9), text("d")]))?;
+        keyed.sync_foreign_key("customer", &customers)?;
+        keyed.insert_row(1, Some(&[text("21"), text("9")]))?;
+        assert!(keyed.insert_row(2, Some(&[text("22"), text("2")])).is_err());
+        Ok(())
+    }
+
+    /// Validation collects every violation and collect mode keeps data untouched
+    #[test]
+    fn validation_report() -> DcsvResult<()> {
+        use crate::{LimiterMode, Value, ValueLimiter, ViolationRule};
+        let mut data = Reader::new().data_from_stream("id,age\n1,20\n1,abc\n2,".as_bytes())?;
+        let limiter = ValueLimiter::from_line(&["number", "", "", "", "", "true"])?;
+
+        assert!(data.set_limiter(1, &limiter, true).is_err());
+        assert_eq!(data.get_cell(1, 1), Some(&Value::Text("abc".to_string())));
+
+        let report = data.set_limiter_with_mode(1, &limiter, LimiterMode::Collect)?;
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            report.violations[0].rule,
+            ViolationRule::Type(crate::ValueType::Number)
+        );
+        assert_eq!(report.violations[1].rule, ViolationRule::Required);
+        assert_eq!(data.get_cell(0, 1), Some(&Value::Number(20)));
+        assert_eq!(data.get_cell(1, 1), Some(&Value::Text("abc".to_string())));
+
+        let report = data.validate();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.violations[0].row, 1);
+        assert_eq!(report.violations[0].columns, vec!["age"]);
+        Ok(())
+    }
+
+    /// Schema is inferred from values and exported
+    #[test]
+    fn infer_schema() -> DcsvResult<()> {
+        use crate::{InferOption, LimiterMode, Schema, ValidatorRegistry, ValueLimiter, ValueType};
+        let mut data = Reader::new().data_from_stream(
+            "id,code,status,name\n1,AB-12,open,Kim\n2,CD-345,closed,Lee\n3,EF-67,open,\n4,GH-89,open,Park"
+                .as_bytes(),
+        )?;
+        let schema = data.infer_schema(
+            &InferOption::new()
+                .max_variants(2)
+                .infer_pattern(true)
+                .infer_required(true),
+        );
+        let id = schema.get_column("id").unwrap();
+        assert_eq!(id.limiter.get_type(), ValueType::Number);
+        assert!(id.limiter.is_required());
+        let code = schema.get_column("code").unwrap();
+        assert_eq!(
+            code.limiter.get_pattern().unwrap().as_str(),
+            "^[A-Z]{2}\\-[0-9]{2,3}$"
+        );
+        let status = schema.get_column("status").unwrap();
+        assert_eq!(status.limiter.get_variant().unwrap().len(), 2);
+        assert!(!schema.get_column("name").unwrap().limiter.is_required());
+
+        assert!(data.apply_schema(&schema, LimiterMode::Strict)?.is_valid());
+        assert!(data
+            .export_schema()
+            .contains("status,Text,open,open closed,,,true"));
+
+        // Quantifiers, commas and double quotes survive a round trip
+        let limiter = ValueLimiter::from_line(&["text", "Kim, \"K\"", "", ""])?;
+        data.set_limiter(3, &limiter, false)?;
+        let exported = data.export_schema();
+        assert!(exported.contains("\"^[A-Z]{2}\\-[0-9]{2,3}$\""));
+        let read = Schema::from_csv(&exported, &ValidatorRegistry::new())?;
+        assert_eq!(read.to_string(), exported);
+
+        // Invalid pattern is an error not a panic
+        assert!(Schema::from_csv(
+            "column,type,default,variant,pattern\ncode,text,A,,[A-Z",
+            &ValidatorRegistry::new()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    /// Table schema json round trips and applies missing values
+    #[test]
+    fn table_schema() -> DcsvResult<()> {
+        use crate::{LimiterMode, Schema, ValidatorRegistry, Value, ValueType};
+        let src = r#"{
+            "fields": [
+                {"name": "id", "type": "integer", "constraints": {"required": true}},
+                {"name": "code", "type": "string", "constraints": {"pattern": "[A-Z]{2}"}},
+                {"name": "status", "type": "string", "unit": "flag",
+                 "constraints": {"enum": ["open", "closed"]}},
+                {"name": "note", "type": "string"}
+            ],
+            "missingValues": ["", "NA"],
+            "primaryKey": "id"
+        }"#;
+        let schema = Schema::from_table_schema(src, &ValidatorRegistry::new())?;
+        assert_eq!(schema.primary_key, vec!["id"]);
+        let code = schema.get_column("code").unwrap();
+        assert!(code.limiter.qualify(&Value::Text("AB".to_string())));
+        assert!(!code.limiter.qualify(&Value::Text("ABC".to_string())));
+        assert_eq!(
+            schema.get_column("status").unwrap().unit.as_deref(),
+            Some("flag")
+        );
+
+        let mut data = Reader::new()
+            .data_from_stream("id,code,status,note\n1,AB,open,NA\n2,CD,closed,late".as_bytes())?;
+        // Failed strict application doesn't modify data
+        let mut invalid = Reader::new()
+            .data_from_stream("id,code,status,note\n1,AB,open,NA\n2,CD,pending,x".as_bytes())?;
+        assert!(invalid.apply_schema(&schema, LimiterMode::Strict).is_err());
+        let mut duplicated = Reader::new()
+            .data_from_stream("id,code,status,note\n1,AB,open,NA\n1,CD,closed,x".as_bytes())?;
+        assert!(duplicated
+            .apply_schema(&schema, LimiterMode::Strict)
+            .is_err());
+        for failed in [&invalid, &duplicated] {
+            assert_eq!(failed.get_cell(0, 0), Some(&Value::Text("1".to_string())));
+            assert_eq!(failed.get_cell(0, 3), Some(&Value::Text("NA".to_string())));
+            assert!(failed.get_primary_key().is_none());
+        }
+
+        assert!(data.apply_schema(&schema, LimiterMode::Strict)?.is_valid());
+        assert_eq!(data.get_cell(0, 3), Some(&Value::Text(String::new())));
+        assert_eq!(data.get_primary_key().unwrap().get_columns(), ["id"]);
+
+        let exported =
+            Schema::from_table_schema(&data.export_table_schema(), &ValidatorRegistry::new())?;
+        assert_eq!(
+            exported.get_column("id").unwrap().limiter.get_type(),
+            ValueType::Number
+        );
+        assert_eq!(exported.primary_key, vec!["id"]);
+        let round_trip =
+            Schema::from_table_schema(&schema.to_table_schema(), &ValidatorRegistry::new())?;
+        assert_eq!(round_trip.missing_values, vec!["", "NA"]);
+        assert!(!round_trip
+            .get_column("code")
+            .unwrap()
+            .limiter
+            .qualify(&Value::Text("ABC".to_string())));
+
+        // Constraints which limiters can't express are refused
+        let src = r#"{"fields":[{"name":"age","type":"integer","constraints":{"minimum":0}}]}"#;
+        assert!(Schema::from_table_schema(src, &ValidatorRegistry::new()).is_err());
+        Ok(())
+    }
+
+    /// Schema diff lists column changes and migrates data
+    #[test]
+    fn schema_migration() -> DcsvResult<()> {
+        use crate::{LimiterMode, Schema, SchemaChange, ValidatorRegistry, Value};
+        let mut data = Reader::new().data_from_stream("id,name,fax\n1,a,x\n2,b,y".as_bytes())?;
+        let target = Schema::from_csv(
+            "column,type,default,variant,pattern,validator,required\nid,Number,,,,,true\nemail,Text,,,,,false\nfull_name,Text,,,,,false",
+            &ValidatorRegistry::new(),
+        )?;
+        let diff = data
+            .get_schema()
+            .diff_with_renames(&target, &[("name", "full_name")])?;
+        assert_eq!(diff.len(), 4);
+        assert!(matches!(&diff.changes[0], SchemaChange::Retyped { column, .. } if column == "id"));
+        assert!(matches!(&diff.changes[1], SchemaChange::Renamed { to, .. } if to == "full_name"));
+        assert!(matches!(&diff.changes[2], SchemaChange::Removed(column) if column == "fax"));
+        assert!(matches!(&diff.changes[3], SchemaChange::Added(column) if column.name == "email"));
+
+        assert!(data.migrate(&diff, LimiterMode::Strict)?.is_valid());
+        assert!(data
+            .export_schema()
+            .ends_with("id,Number,,,,,true\nemail,Text,,,,,false\nfull_name,Text,,,,,false\n"));
+        assert_eq!(data.get_cell(1, 0), Some(&Value::Number(2)));
+        assert_eq!(data.get_cell(1, 2), Some(&Value::Text("b".to_string())));
+        assert!(data.get_schema().diff(&target).is_empty());
+
+        // Columns can swap their names
+        let diff = data
+            .get_schema()
+            .diff_with_renames(&target, &[("email", "full_name"), ("full_name", "email")])?;
+        data.migrate(&diff, LimiterMode::Strict)?;
+        assert_eq!(data.get_cell(1, 1), Some(&Value::Text("b".to_string())));
+        assert!(data.get_schema().diff(&target).is_empty());
+        Ok(())
+    }
+
+    /// Rows are sorted by multiple keys with stable order
+    #[test]
+    fn sort_rows() -> DcsvResult<()> {
+        use crate::{NullOrder, SortKey, Value};
+        let src = "name,age\nb,3\na,\nc,3\nd,1";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        data.sort_rows(&[SortKey::desc(1).nulls(NullOrder::First), SortKey::desc(0)])?;
+        let names = (0..data.get_row_count())
+            .map(|i| data.get_cell(i, 0).unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "c", "b", "d"]);
+
+        let mut array = Reader::new().array_from_stream(src.as_bytes())?;
+        array.sort_rows(&[SortKey::asc(1)])?;
+        assert_eq!(array.get_cell(0, 0), Some(&Value::Text("d".to_string())));
+        assert_eq!(array.get_cell(1, 0), Some(&Value::Text("b".to_string())));
+        assert_eq!(array.get_cell(3, 0), Some(&Value::Text("a".to_string())));
+        assert!(array.sort_rows(&[SortKey::asc(2)]).is_err());
+        Ok(())
+    }
+
+    /// Comparators order texts naturally or by collation
+    #[test]
+    fn comparator() -> DcsvResult<()> {
+        use crate::{Comparator, SortKey, Value};
+        let mut data = Reader::new().data_from_stream(
+            "file,word\nfile10,zebra\nfile2,éclair\nfile1,Eclair\n,apple".as_bytes(),
+        )?;
+        let column = |data: &crate::VirtualData, col: usize| {
+            (0..data.get_row_count())
+                .map(|i| data.get_cell(i, col).unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        data.sort_rows(&[SortKey::asc(0).comparator(Comparator::Natural)])?;
+        assert_eq!(column(&data, 0), vec!["file1", "file2", "file10", ""]);
+        data.sort_rows(&[SortKey::asc(1).comparator(Comparator::Collation)])?;
+        assert_eq!(column(&data, 1), vec!["apple", "Eclair", "éclair", "zebra"]);
+
+        assert_eq!(
+            data.get_max_value(0, Comparator::Natural),
+            Some(&Value::Text("file10".to_string()))
+        );
+        assert_eq!(
+            data.get_max_value(0, Comparator::Default),
+            Some(&Value::Text("file2".to_string()))
+        );
+        assert_eq!(
+            data.get_min_value(1, Comparator::CaseInsensitive),
+            Some(&Value::Text("apple".to_string()))
+        );
+        Ok(())
+    }
+
+    /// Query filters rows of both containers
+    #[test]
+    fn query() -> DcsvResult<()> {
+        use crate::Query;
+        let src = "name,age,city\nKim,35,Daegu\nLee,40,Seoul\nPark,20,Busan\nKang,25,Incheon";
+        let data = Reader::new().data_from_stream(src.as_bytes())?;
+        let query = Query::parse(r#"age >= 30 && name ~ /^K/ || city in ("Seoul", "Busan")"#)?;
+        assert_eq!(data.query_rows(&query)?, vec![0, 1, 2]);
+        let query = Query::parse("!(age < 30) && city != \"Seoul\"")?;
+        let filtered = data.filter(&query)?;
+        assert_eq!(filtered.get_row_count(), 1);
+        assert_eq!(filtered.get_cell(0, 0).unwrap().to_string(), "Kim");
+
+        let array = Reader::new().array_from_stream(src.as_bytes())?;
+        assert_eq!(
+            array
+                .filter(&Query::parse("name !~ \"a\"")?)?
+                .get_row_count(),
+            2
+        );
+        // Only unquoted literals are compared numerically
+        assert_eq!(
+            data.query_rows(&Query::parse("age < 100")?)?,
+            vec![0, 1, 2, 3]
+        );
+        assert!(data.query_rows(&Query::parse("age < \"100\"")?)?.is_empty());
+        let codes = Reader::new().data_from_stream("code\n007\n7".as_bytes())?;
+        assert_eq!(
+            codes.query_rows(&Query::parse("code == \"007\"")?)?,
+            vec![0]
+        );
+        assert_eq!(codes.query_rows(&Query::parse("code == 7")?)?, vec![0, 1]);
+        assert_eq!(data.query_rows(&Query::parse("city != 1")?)?.len(), 4);
+        assert!(data.query_rows(&Query::parse("zip == 1")?).is_err());
+        assert!(Query::parse("age >= && name").is_err());
+        assert!(Query::parse("age = 1").is_err());
+        Ok(())
+    }
+
+    /// Rows are grouped and aggregated into typed columns
+    #[test]
+    fn group_by() -> DcsvResult<()> {
+        use crate::{Aggregate, AggregateFn, Value, ValueType};
+        let data = Reader::new().data_from_stream(
+            "category,item,price\nfruit,apple,3\nveg,kale,2\nfruit,pear,4\nfruit,apple,6"
+                .as_bytes(),
+        )?;
+        let grouped = data.group_by(
+            &["category"],
+            &[
+                Aggregate::new("item", AggregateFn::Count),
+                Aggregate::new("item", AggregateFn::CountDistinct),
+                Aggregate::new("price", AggregateFn::Sum).alias("total"),
+                Aggregate::new("price", AggregateFn::Mean),
+                Aggregate::new("item", AggregateFn::Concat("|".to_string())),
+                Aggregate::new("item", AggregateFn::Last),
+            ],
+        )?;
+        assert_eq!(grouped.get_row_count(), 2);
+        assert_eq!(grouped.columns[3].name, "total");
+        assert_eq!(grouped.columns[3].column_type, ValueType::Number);
+        let row = grouped
+            .get_row_iterator(0)?
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            row,
+            vec![
+                "fruit",
+                "3",
+                "2",
+                "13",
+                "4.333333333333333",
+                "apple|pear|apple",
+                "apple"
+            ]
+        );
+        assert_eq!(grouped.get_cell(1, 3), Some(&Value::Number(2)));
+        assert!(data
+            .group_by(&["category"], &[Aggregate::new("item", AggregateFn::Sum)])
+            .is_err());
+
+        // Empty columns aggregate whole rows
+        let total = data.group_by(
+            &[] as &[&str],
+            &[Aggregate::new("price", AggregateFn::Mean)],
+        )?;
+        assert_eq!(total.get_row_count(), 1);
+        assert_eq!(total.get_cell(0, 0), Some(&Value::Text("3.75".to_string())));
+        Ok(())
+    }
+
+    /// Joins match rows on keys and suffix clashing columns
+    #[test]
+    fn join() -> DcsvResult<()> {
+        use crate::{JoinKind, JoinOption, Value};
+        let orders = Reader::new().data_from_stream(
+            "id,customer,name\n10,1,pen\n11,3,ink\n12,1,cup\n13,,box".as_bytes(),
+        )?;
+        let customers = Reader::new().data_from_stream("cid,name\n1,Kim\n2,Lee".as_bytes())?;
+        let join = |kind| orders.join(&customers, &["customer"], &["cid"], &JoinOption::new(kind));
+
+        let inner = join(JoinKind::Inner)?;
+        assert_eq!(inner.get_row_count(), 2);
+        assert_eq!(inner.columns[2].name, "name_left");
+        assert_eq!(inner.columns[3].name, "name_right");
+        assert_eq!(inner.get_cell(1, 3).unwrap().to_string(), "Kim");
+        assert_eq!(join(JoinKind::Left)?.get_row_count(), 4);
+        let right = join(JoinKind::Right)?;
+        assert_eq!(right.get_row_count(), 3);
+        assert_eq!(right.get_cell(2, 1).unwrap().to_string(), "2");
+        assert_eq!(join(JoinKind::Full)?.get_row_count(), 5);
+        let anti = join(JoinKind::Anti)?;
+        assert_eq!(anti.get_column_count(), 3);
+        assert_eq!(anti.get_row_count(), 2);
+
+        let orders = Reader::new().array_from_stream("id,customer\n10,1\n11,2".as_bytes())?;
+        let customers = Reader::new().array_from_stream("customer,name\n2,Lee".as_bytes())?;
+        let joined = orders.join(
+            &customers,
+            &["customer"],
+            &["0"],
+            &JoinOption::new(JoinKind::Inner),
+        )?;
+        assert_eq!(joined.get_column_count(), 3);
+        assert_eq!(joined.get_cell(0, 2).unwrap().to_string(), "Lee");
+
+        // Keys are matched by text form and suffixed names stay unique
+        let left = Reader::new().array_from_stream("key,name,name_right\n1,a,b".as_bytes())?;
+        let mut right = Reader::new().array_from_stream("key,name\n1,c".as_bytes())?;
+        right.set_cell(0, 0, Value::Number(1))?;
+        let joined = left.join(
+            &right,
+            &["key"],
+            &["key"],
+            &JoinOption::new(JoinKind::Inner),
+        )?;
+        assert_eq!(joined.get_row_count(), 1);
+        assert_eq!(joined.columns[3].name, "name_right_2");
+        Ok(())
+    }
+
+    /// Arrays from reader have metas for every column
+    #[test]
+    fn array_metas() -> DcsvResult<()> {
+        use crate::{VCont, Value};
+        let mut array = Reader::new().array_from_stream("a,b\n1,2".as_bytes())?;
+        assert_eq!(array.metas.len(), 2);
+        array.set_cell(0, 1, Value::Text("wide".to_string()))?;
+        assert_eq!(array.metas[1].max_unicode_width, 4);
+        Ok(())
+    }
+
+    /// Concatenation lines up columns, reports conflicts and tags sources
+    #[test]
+    fn concat() -> DcsvResult<()> {
+        use crate::{ConcatOption, LimiterMode, Value, ValueLimiter};
+        let mut january = Reader::new().data_from_stream("id,amount\n1,10\n2,20".as_bytes())?;
+        january.set_limiter(1, &ValueLimiter::from_line(&["number", "0", "", ""])?, true)?;
+        let february =
+            Reader::new().data_from_stream("memo,amount\nlate,30\nfree,none".as_bytes())?;
+
+        let option = ConcatOption::new().source_column("source", "jan", "feb");
+        assert!(january.concat(&february, &option).is_err());
+        assert_eq!(january.get_column_count(), 2);
+
+        let report = january.concat(&february, &option.clone().mode(LimiterMode::Overwrite))?;
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations.violations[0].row, 1);
+        assert_eq!(
+            january
+                .columns
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["id", "amount", "memo", "source"]
+        );
+        assert_eq!(january.get_cell(2, 1), Some(&Value::Number(30)));
+        assert_eq!(january.get_cell(3, 1), Some(&Value::Number(0)));
+        assert_eq!(january.get_cell(0, 3).unwrap().to_string(), "jan");
+        assert_eq!(january.get_cell(3, 3).unwrap().to_string(), "feb");
+
+        // Appended rows are checked against each other before any change
+        let mut keyed = Reader::new().data_from_stream("id\n1".as_bytes())?;
+        keyed.add_unique_constraint(&["id"])?;
+        let duplicated = Reader::new().data_from_stream("id,memo\n2,a\n2,b".as_bytes())?;
+        assert!(keyed.concat(&duplicated, &ConcatOption::new()).is_err());
+        assert_eq!(keyed.get_column_count(), 1);
+        assert_eq!(keyed.get_row_count(), 1);
+        let appended = Reader::new().data_from_stream("id\n2".as_bytes())?;
+        keyed.concat(&appended, &ConcatOption::new())?;
+        assert!(keyed
+            .insert_row(2, Some(&[Value::Text("2".to_string())]))
+            .is_err());
+
+        let mut a = Reader::new().array_from_stream("id,name\n1,a".as_bytes())?;
+        let b = Reader::new().array_from_stream("name,id\nb,2".as_bytes())?;
+        a.concat(&b, &ConcatOption::new());
+        assert_eq!(a.get_cell(1, 0).unwrap().to_string(), "2");
+        Ok(())
+    }
+
+    /// Deduplication removes exact duplicates or duplicates by key columns
+    #[test]
+    fn dedup() -> DcsvResult<()> {
+        use crate::{Keep, Value};
+        let src = "id,name\n1,a\n2,b\n1,a\n3,b\n1,c";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        assert_eq!(data.duplicates(&["name"])?, vec![vec![0, 2], vec![1, 3]]);
+        assert_eq!(data.duplicates(&[] as &[&str])?, vec![vec![0, 2]]);
+
+        let mut last = data.clone();
+        assert_eq!(last.dedup(&["id"], Keep::Last)?, 2);
+        assert_eq!(last.get_row_count(), 3);
+        assert_eq!(last.get_cell(2, 1), Some(&Value::Text("c".to_string())));
+
+        let mut none = data.clone();
+        assert_eq!(none.dedup(&["id"], Keep::None)?, 3);
+        assert_eq!(none.get_row_count(), 2);
+
+        assert_eq!(data.dedup(&[] as &[&str], Keep::First)?, 1);
+        assert_eq!(data.get_row_count(), 4);
+        data.set_primary_key(&["id", "name"])?;
+
+        let mut array = Reader::new().array_from_stream(src.as_bytes())?;
+        assert_eq!(array.duplicates(&["0"])?, vec![vec![0, 2, 4]]);
+        assert_eq!(array.dedup(&["name"], Keep::First)?, 2);
+        assert_eq!(array.get_row_count(), 3);
+        Ok(())
+    }
+
+    /// Pivot spreads values into columns and melt gathers them back
+    #[test]
+    fn pivot_melt() -> DcsvResult<()> {
+        use crate::{AggregateFn, Value, ValueLimiter, MELT_VALUE, MELT_VARIABLE};
+        let src = "store,year,sales\na,2023,10\na,2024,20\nb,2023,5\na,2023,1\nb,,7";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        data.set_limiter(2, &ValueLimiter::from_line(&["number", "0", "", ""])?, true)?;
+
+        let wide = data.pivot(&["store"], "year", "sales", AggregateFn::Sum)?;
+        assert_eq!(
+            wide.columns
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["store", "sales_2023", "sales_2024"]
+        );
+        assert_eq!(wide.get_row_count(), 2);
+        assert_eq!(wide.get_cell(0, 1), Some(&Value::Number(11)));
+        assert_eq!(wide.get_cell(1, 2), Some(&Value::Number(0)));
+
+        let long = wide.melt(&["store"], &[] as &[&str])?;
+        assert_eq!(long.get_row_count(), 4);
+        assert_eq!(long.columns[1].name, MELT_VARIABLE);
+        assert_eq!(long.columns[2].name, MELT_VALUE);
+        assert_eq!(long.get_cell(1, 1).unwrap().to_string(), "sales_2024");
+        assert_eq!(long.get_cell(1, 2), Some(&Value::Number(20)));
+
+        let mixed = data.melt(&["store"], &["year", "sales"])?;
+        assert_eq!(mixed.columns[2].column_type, crate::ValueType::Text);
+        assert_eq!(mixed.get_cell(1, 2).unwrap().to_string(), "10");
+        Ok(())
+    }
+
+    /// Headerless csv gets arbitrary column names beyond alphabets
+    #[test]
+    fn arbitrary_columns() -> DcsvResult<()> {
+        let line = vec!["1"; 28].join(",");
+        let data = Reader::new()
+            .has_header(false)
+            .data_from_stream(line.as_bytes())?;
+        assert_eq!(data.columns[0].name, "a");
+        assert_eq!(data.columns[25].name, "z");
+        assert_eq!(data.columns[27].name, "bb");
+        Ok(())
+    }
+
+    /// Transpose swaps rows and columns with an optional header column
+    #[test]
+    fn transpose() -> DcsvResult<()> {
+        let src = "key,dev,prod\nhost,localhost,example.com\nport,8080,80";
+        let array = Reader::new().array_from_stream(src.as_bytes())?;
+
+        let swapped = array.transpose(true);
+        assert_eq!(
+            swapped
+                .columns
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["key", "host", "port"]
+        );
+        assert_eq!(swapped.get_row_count(), 2);
+        assert_eq!(swapped.get_cell(1, 0).unwrap().to_string(), "prod");
+        assert_eq!(swapped.get_cell(1, 2).unwrap().to_string(), "80");
+        assert_eq!(swapped.transpose(true).to_string(), array.to_string());
+
+        let plain = array.transpose(false);
+        assert_eq!(plain.get_column_count(), 3);
+        assert_eq!(plain.get_row_count(), 3);
+        assert_eq!(plain.columns[0].name, "a");
+        assert_eq!(plain.get_cell(0, 0).unwrap().to_string(), "key");
+
+        let wide = Reader::new()
+            .has_header(false)
+            .array_from_stream(vec!["0"; 30].join(",").as_bytes())?;
+        assert_eq!(wide.columns[26].name, "aa");
+        assert_eq!(wide.transpose(false).get_row_count(), 30);
+
+        let data = Reader::new().data_from_stream(src.as_bytes())?;
+        let swapped = data.transpose(true)?;
+        assert_eq!(swapped.get_cell(1, 2).unwrap().to_string(), "80");
+        assert_eq!(swapped.transpose(true)?.to_string(), data.to_string());
+        let duplicate = Reader::new().data_from_stream("key,value\na,1\na,2".as_bytes())?;
+        assert!(duplicate.transpose(true).is_err());
+        Ok(())
+    }
+
+    /// Computed columns are materialized or recomputed on edits
+    #[test]
+    fn computed_column() -> DcsvResult<()> {
+        use crate::{Expression, Value, ValueType};
+        let src = "first,last,price,qty\nJohn,Doe,3,4\nJane,Roe,5,";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+
+        let total = Expression::parse("price * qty")?;
+        data.insert_computed_column(4, "total", ValueType::Number, &total, true)?;
+        let full = Expression::parse("first || ' ' || last")?;
+        data.insert_computed_column(5, "full", ValueType::Text, &full, false)?;
+        assert_eq!(data.get_cell(0, 4), Some(&Value::Number(12)));
+        assert_eq!(data.get_cell(1, 4), Some(&Value::Number(0)));
+        assert_eq!(data.get_cell(1, 5).unwrap().to_string(), "Jane Roe");
+
+        data.set_cell(1, 3, Value::Text("2".to_string()))?;
+        assert_eq!(data.get_cell(1, 4), Some(&Value::Number(10)));
+        data.set_cell(0, 0, Value::Text("Jim".to_string()))?;
+        assert_eq!(data.get_cell(0, 5).unwrap().to_string(), "John Doe");
+        assert!(data.set_cell(0, 4, Value::Number(1)).is_err());
+        assert!(data
+            .set_cell(0, 2, Value::Text("free".to_string()))
+            .is_err());
+        assert_eq!(data.get_cell(0, 2).unwrap().to_string(), "3");
+
+        data.insert_row(2, None)?;
+        assert_eq!(data.get_cell(2, 4), Some(&Value::Number(0)));
+        data.rename_column(2, "unit_price")?;
+        assert_eq!(
+            data.get_derived_columns()[0].expression.to_string(),
+            "unit_price * qty"
+        );
+        data.set_column(3, Value::Text("10".to_string()))?;
+        assert_eq!(data.get_cell(0, 4), Some(&Value::Number(30)));
+        assert!(data.set_column(3, Value::Text("ten".to_string())).is_err());
+        assert_eq!(data.get_cell(1, 3).unwrap().to_string(), "10");
+
+        // Direct edits are reflected by recomputation
+        data.rows[0].update_cell_value("unit_price", Value::Text("4".to_string()));
+        data.recompute_derived()?;
+        assert_eq!(data.get_cell(0, 4), Some(&Value::Number(40)));
+        assert_eq!(data.get_cell(1, 4), Some(&Value::Number(50)));
+
+        assert!(Expression::parse("SUM(price:qty) + 1").is_ok());
+        assert!(Expression::parse("price:qty").is_err());
+        assert!(data
+            .insert_computed_column(0, "ratio", ValueType::Number, &"qty / 0".parse()?, false)
+            .is_err());
+        assert_eq!(data.get_column_count(), 6);
+        assert_eq!(
+            Expression::parse("(a - (b - c)) * -d || `e f`")?.to_string(),
+            "(a - (b - c)) * -d || `e f`"
+        );
+        Ok(())
+    }
+
+    /// Formulas recompute in a dependency order and follow structural edits
+    #[test]
+    fn formula() -> DcsvResult<()> {
+        use crate::{Value, FORMULA_REF_ERROR, FORMULA_VALUE_ERROR};
+        let mut array = Reader::new().array_from_stream("a,b\n1,\n2,\n3,".as_bytes())?;
+        array.set_formula(0, 1, "=SUM(A1:A3)")?;
+        array.set_formula(1, 1, "=B1 * 2")?;
+        array.set_formula(2, 1, "b2 - a1")?;
+        assert_eq!(array.get_cell(2, 1), Some(&Value::Number(11)));
+        assert_eq!(array.get_formula(2, 1), Some("=B2 - A1".to_string()));
+
+        array.set_cell(0, 0, Value::Number(4))?;
+        assert_eq!(array.get_cell(0, 1), Some(&Value::Number(9)));
+        assert_eq!(array.get_cell(2, 1), Some(&Value::Number(14)));
+        assert!(array.set_formula(0, 1, "=B3").is_err());
+        assert!(array.set_formula(0, 1, "=price").is_err());
+        assert_eq!(array.get_formula(0, 1), Some("=SUM(A1:A3)".to_string()));
+
+        array.insert_row(0, None)?;
+        assert_eq!(array.get_formula(3, 1), Some("=B3 - A2".to_string()));
+        assert_eq!(array.get_formula(1, 1), Some("=SUM(A2:A4)".to_string()));
+        array.delete_row(1);
+        assert_eq!(array.get_cell(2, 1).unwrap().to_string(), FORMULA_REF_ERROR);
+
+        array.set_cell(1, 1, Value::Number(1))?;
+        assert_eq!(array.get_formula(1, 1), None);
+        array.set_formula(0, 0, "A3 / B2")?;
+        array.set_cell(1, 1, Value::Number(0))?;
+        assert_eq!(
+            array.get_cell(0, 0).unwrap().to_string(),
+            FORMULA_VALUE_ERROR
+        );
+        assert_eq!(array.get_formula_cells(), vec![(0, 0), (2, 1)]);
+
+        // Moving a column moves formulas and shifts references, and undo moves them back
+        let mut array = Reader::new().array_from_stream("a,b,c\n1,2,".as_bytes())?;
+        array.set_formula(0, 2, "=A1 + B1 * 10")?;
+        let mut history = crate::History::new(array);
+        history.move_column(2, 0)?;
+        assert_eq!(
+            history.get_data().get_formula(0, 0),
+            Some("=B1 + C1 * 10".to_string())
+        );
+        assert_eq!(history.get_data().get_cell(0, 0), Some(&Value::Number(21)));
+        history.undo()?;
+        assert_eq!(
+            history.get_data().get_formula(0, 2),
+            Some("=A1 + B1 * 10".to_string())
+        );
+        assert_eq!(history.get_data().get_cell(0, 2), Some(&Value::Number(21)));
+        Ok(())
+    }
+
+    /// Find and replace cells with capture groups, scopes and limiters
+    #[test]
+    fn find_replace() -> DcsvResult<()> {
+        use crate::{LimiterMode, Scope, Value, ValueLimiter};
+        let src = "name,phone,age\nKim,010-1234,30\nLee,010-5678,4x\nPark,,40";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        assert_eq!(data.find("^0", &Scope::All)?, vec![(0, 1), (1, 1)]);
+        assert_eq!(
+            data.find(
+                "e",
+                &Scope::Range {
+                    from: (2, 0),
+                    to: (0, 0)
+                }
+            )?,
+            vec![(1, 0)]
+        );
+        assert!(data.find("(", &Scope::All).is_err());
+        assert!(data.find("a", &Scope::Columns(vec![3])).is_err());
+
+        let report = data.replace(
+            r"(\d{3})-(\d+)",
+            "($1) $2",
+            &Scope::Columns(vec![1]),
+            LimiterMode::Strict,
+        )?;
+        assert_eq!(report.replacements, 2);
+        assert_eq!(data.get_cell(1, 1).unwrap().to_string(), "(010) 5678");
+
+        data.set_limiter(
+            2,
+            &ValueLimiter::from_line(&["number", "0", "", ""])?,
+            false,
+        )?;
+        data.set_cell(1, 2, Value::Number(45))?;
+        assert!(data
+            .replace("5", "x", &Scope::All, LimiterMode::Strict)
+            .is_err());
+        assert_eq!(data.get_cell(1, 1).unwrap().to_string(), "(010) 5678");
+        let report = data.replace("5", "x", &Scope::All, LimiterMode::Collect)?;
+        assert_eq!(report.replacements, 1);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(data.get_cell(1, 2), Some(&Value::Number(45)));
+
+        let mut array = Reader::new().array_from_stream(src.as_bytes())?;
+        array.set_formula(2, 1, "A1")?;
+        assert_eq!(array.replace("K", "G", &Scope::All)?, 1);
+        assert_eq!(array.get_cell(2, 1).unwrap().to_string(), "Gim");
+
+        // Keys are checked against a final state
+        let mut keys = Reader::new().data_from_stream("code\naa\na".as_bytes())?;
+        keys.add_unique_constraint(&["code"])?;
+        keys.replace("^a", "", &Scope::All, LimiterMode::Strict)?;
+        assert_eq!(keys.get_cell(0, 0).unwrap().to_string(), "a");
+        assert!(keys
+            .replace("^$", "a", &Scope::All, LimiterMode::Strict)
+            .is_err());
+        assert_eq!(keys.get_cell(1, 0).unwrap().to_string(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn history() -> DcsvResult<()> {
+        use crate::{History, Reader, SortKey, VCont, Value, VirtualArray};
+        let src = "id,name\n1,a\n2,b\n3,c";
+        let data = Reader::new().data_from_stream(src.as_bytes())?;
+        let mut history = History::new(data).depth(3);
+        assert!(!history.can_undo());
+
+        history.set_cell(0, 1, Value::Text("x".to_string()))?;
+        history.begin_group();
+        history.insert_row(
+            3,
+            Some(&[Value::Text("4".to_string()), Value::Text("d".to_string())]),
+        )?;
+        history.rename_column(1, "label")?;
+        history.end_group();
+        history.sort_rows(&[SortKey::desc(0)])?;
+        history.delete_column(0)?;
+        assert!(history
+            .set_cell(10, 0, Value::Text("y".to_string()))
+            .is_err());
+        assert_eq!(history.get_column_count(), 1);
+
+        // Depth is 3, thus a first edit is dropped
+        assert!(history.undo()?);
+        assert_eq!(history.get_cell(0, 0).unwrap().to_string(), "4");
+        assert!(history.undo()?);
+        assert!(history.undo()?);
+        assert_eq!(history.get_row_count(), 3);
+        assert_eq!(history.get_data().columns[1].name, "name");
+        assert!(!history.undo()?);
+        assert_eq!(history.get_cell(0, 1).unwrap().to_string(), "x");
+
+        assert!(history.redo()?);
+        assert_eq!(history.get_row_count(), 4);
+        assert_eq!(history.get_data().columns[1].name, "label");
+        history.delete_row(0);
+        assert!(!history.can_redo());
+        assert!(history.undo()?);
+        assert_eq!(history.get_cell(0, 1).unwrap().to_string(), "x");
+
+        let array = Reader::new().array_from_stream(src.as_bytes())?;
+        let mut history = History::new(array);
+        history.record(|array: &mut VirtualArray| array.set_formula(2, 1, "A1 + A2"))?;
+        history.delete_row(0);
+        assert_eq!(history.get_cell(1, 1).unwrap().to_string(), "#REF!");
+        history.undo()?;
+        assert_eq!(history.get_cell(2, 1).unwrap().to_string(), "3");
+        history.undo()?;
+        assert!(history.get_data().get_formula(2, 1).is_none());
+
+        // Formulas come back when edits which removed, moved or broke them are undone
+        history.record(|array: &mut VirtualArray| array.set_formula(2, 1, "A1 + A2"))?;
+        history.set_column(1, Value::Text("z".to_string()))?;
+        assert!(history.get_data().get_formula(2, 1).is_none());
+        history.undo()?;
+        assert!(history.get_data().get_formula(2, 1).is_some());
+        history.sort_rows(&[SortKey::desc(0)])?;
+        assert_eq!(history.get_data().get_formula_cells(), vec![(0, 1)]);
+        history.apply_all(|value| *value = Value::Text(value.to_string().repeat(2)));
+        assert_eq!(history.get_cell(0, 1).unwrap().to_string(), "33");
+        history.undo()?;
+        assert_eq!(history.get_cell(0, 1).unwrap().to_string(), "3");
+        history.undo()?;
+        assert_eq!(history.get_data().get_formula_cells(), vec![(2, 1)]);
+        history.delete_column(0)?;
+        history.undo()?;
+        assert_eq!(history.get_cell(2, 1).unwrap().to_string(), "3");
+        history.redo()?;
+        assert_eq!(history.get_column_count(), 1);
+
+        // Deleted column comes back with its key constraint
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        data.set_primary_key(&["id"])?;
+        let mut history = History::new(data);
+        history.delete_column(0)?;
+        assert!(history.get_data().get_primary_key().is_none());
+        history.undo()?;
+        assert_eq!(history.get_cell(2, 0).unwrap().to_string(), "3");
+        assert!(history
+            .insert_row(3, Some(&[Value::Text("1".to_string()), Value::default()]))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn transaction() -> DcsvResult<()> {
+        use crate::{History, Reader, VCont, Value, ValueLimiter};
+        let src = "id,qty\n1,10\n2,20\n3,30";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        data.set_limiter(
+            1,
+            &ValueLimiter::from_line(&["number", "0", "", ""])?,
+            false,
+        )?;
+        let result = data.transaction(|tx| {
+            for (index, qty) in ["11", "22", "x"].iter().enumerate() {
+                tx.set_row(
+                    index,
+                    &[
+                        Value::Text((index + 1).to_string()),
+                        Value::Text(qty.to_string()),
+                    ],
+                )?;
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(data.get_cell(0, 1), Some(&Value::Number(10)));
+
+        let count = data.transaction(|tx| {
+            tx.insert_row(3, None)?;
+            tx.set_cell(3, 1, Value::Number(40))?;
+            Ok(tx.get_row_count())
+        })?;
+        assert_eq!(count, 4);
+        assert_eq!(data.get_cell(3, 1), Some(&Value::Number(40)));
+
+        let mut history = History::new(data);
+        history.transaction(|tx| {
+            tx.delete_row(0);
+            tx.rename_column(1, "amount")
+        })?;
+        assert_eq!(history.get_row_count(), 3);
+        history.undo()?;
+        assert_eq!(history.get_row_count(), 4);
+        assert_eq!(history.get_data().columns[1].name, "qty");
+        assert!(!history.can_undo());
+
+        // Failed transaction reverts its edits in place and keeps an enclosing group
+        history.begin_group();
+        history.set_cell(0, 1, Value::Number(5))?;
+        let result = history.transaction(|tx| {
+            tx.delete_column(0)?;
+            tx.set_cell(0, 0, Value::Text("x".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(history.get_column_count(), 2);
+        assert_eq!(history.get_cell(0, 1), Some(&Value::Number(5)));
+        history.end_group();
+        history.undo()?;
+        assert_eq!(history.get_cell(0, 1), Some(&Value::Number(10)));
+        assert!(!history.can_undo());
+        Ok(())
+    }
+
+    #[test]
+    fn change_tracking() -> DcsvResult<()> {
+        use crate::{
+            Aggregate, AggregateFn, CellAlignType, ChangeState, JoinKind, JoinOption, Query,
+            Reader, SortKey, VCont, Value, ValueLimiter,
+        };
+        let src = "id,name\n1,a\n2,b\n3,c";
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        assert!(!data.is_dirty());
+
+        data.set_cell(1, 1, Value::Text("x".to_string()))?;
+        data.insert_row(0, None)?;
+        data.delete_row(3);
+        data.insert_column(2, "note")?;
+        data.rename_column(0, "key")?;
+        assert!(data.is_dirty());
+        assert_eq!(data.get_cell_state(2, 1), Some(ChangeState::Modified));
+        assert_eq!(data.get_cell_state(1, 1), Some(ChangeState::Clean));
+        assert_eq!(data.get_cell_state(1, 2), Some(ChangeState::Inserted));
+        assert_eq!(data.get_row_state(0), Some(ChangeState::Inserted));
+        assert_eq!(data.get_column_state(0), Some(ChangeState::Modified));
+        assert_eq!(data.get_original_row_index(2), Some(1));
+
+        let changes = data.get_changes();
+        assert_eq!(changes.inserted_rows, vec![0]);
+        assert_eq!(changes.modified_rows, vec![2]);
+        assert_eq!(changes.deleted_rows, vec![2]);
+        assert_eq!(changes.inserted_columns, vec![2]);
+        assert_eq!(changes.modified_columns, vec![0, 1]);
+        assert_eq!(changes.modified_cells, vec![(2, 1)]);
+
+        let table = data.get_change_table(CellAlignType::None);
+        assert_eq!(table[0][2], ("note".to_string(), ChangeState::Inserted));
+        assert_eq!(table[3][1], ("x".to_string(), ChangeState::Modified));
+
+        data.mark_clean();
+        assert!(!data.is_dirty());
+        data.delete_column(2)?;
+        assert_eq!(data.get_changes().deleted_columns, vec!["note".to_string()]);
+
+        // Reverting a value and reordering rows
+        let mut array = Reader::new().array_from_stream(src.as_bytes())?;
+        array.set_cell(0, 1, Value::Text("z".to_string()))?;
+        array.set_cell(0, 1, Value::Text("a".to_string()))?;
+        assert!(!array.is_dirty());
+        array.sort_rows(&[SortKey::desc(0)])?;
+        assert!(array.get_changes().is_empty());
+        assert!(array.is_dirty());
+        assert_eq!(array.get_original_row_index(0), Some(2));
+
+        // Values and their states move with a column
+        array.move_column(1, 0)?;
+        assert_eq!(array.get_cell(0, 0), Some(&Value::Text("c".to_string())));
+        assert_eq!(array.get_cell_state(0, 0), Some(ChangeState::Clean));
+        array.set_cell(1, 0, Value::Text("y".to_string()))?;
+        assert_eq!(array.get_cell_state(1, 0), Some(ChangeState::Modified));
+        assert_eq!(array.get_cell_state(1, 1), Some(ChangeState::Clean));
+        assert_eq!(array.get_changes().modified_cells, vec![(1, 0)]);
+
+        // Derived containers start clean
+        let data = Reader::new().data_from_stream(src.as_bytes())?;
+        let grouped = data.group_by(&["name"], &[Aggregate::new("id", AggregateFn::Count)])?;
+        let joined = data.join(&data, &["id"], &["id"], &JoinOption::new(JoinKind::Inner))?;
+        let filtered = data.filter(&Query::parse("id > 1")?)?;
+        for derived in [&grouped, &joined, &filtered, &data.transpose(true)?] {
+            assert!(!derived.is_dirty());
+            assert_eq!(derived.get_cell_state(0, 0), Some(ChangeState::Clean));
+        }
+        let long = data.melt(&["id"], &[] as &[&str])?;
+        assert!(long.get_changes().is_empty());
+
+        // Type conversion keeps texts of values, thus it is not a change
+        let mut data = Reader::new().data_from_stream(src.as_bytes())?;
+        data.set_limiter(
+            0,
+            &ValueLimiter::from_line(&["number", "0", "", ""])?,
+            false,
+        )?;
+        assert!(!data.is_dirty());
+        data.apply_all(|value| *value = Value::Text(value.to_string().to_uppercase()));
+        assert_eq!(
+            data.get_changes().modified_cells,
+            vec![(0, 1), (1, 1), (2, 1)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn data_diff() -> DcsvResult<()> {
+        use crate::{DataChange, Reader, VCont, Value};
+        let old = Reader::new()
+            .data_from_stream("id,name,qty,memo\n1,a,10,x\n2,b,20,y\n3,c,30,z".as_bytes())?;
+        let mut new = Reader::new()
+            .data_from_stream("id,qty,name,note\n3,30,c,n\n4,40,e,n\n1,15,a,n".as_bytes())?;
+        new.set_cell(0, 2, Value::Text("c, d".to_string()))?;
+        let diff = old.diff(&new, &["0"])?;
+        assert_eq!(diff.key_columns, vec!["id".to_string()]);
+        assert_eq!(diff.len(), 8);
+        assert_eq!(diff.changes[0], DataChange::ColumnAdded("note".to_string()));
+        assert_eq!(
+            diff.changes[1],
+            DataChange::ColumnRemoved("memo".to_string())
+        );
+        assert_eq!(
+            diff.changes[4],
+            DataChange::RowRemoved {
+                key: vec![Value::Text("2".to_string())],
+                row: 1
+            }
+        );
+        assert_eq!(
+            diff.to_string().lines().nth(5),
+            Some("~ row (3) name : \"c\" -> \"c, d\"")
+        );
+        assert_eq!(
+            diff.to_csv().lines().take(4).collect::<Vec<_>>(),
+            vec![
+                "change,id,column,old,new",
+                "column_added,,note,,",
+                "column_removed,,memo,,",
+                "column_moved,,qty,2,1",
+            ]
+        );
+        assert_eq!(
+            diff.to_csv().lines().nth(6),
+            Some("cell_changed,3,name,c,\"c, d\"")
+        );
+
+        assert!(old.diff(&old, &["name"])?.is_empty());
+        assert!(old.diff(&new, &["memo"]).is_err());
+        assert!(old.diff(&new, &[] as &[&str]).is_err());
+        let duplicated = Reader::new().data_from_stream("id,name\n1,a\n1,b".as_bytes())?;
+        assert!(old.diff(&duplicated, &["id"]).is_err());
+
+        // Numeric header is matched by name before an index
+        let numeric = Reader::new().data_from_stream("2,name,3\na,x,1\nb,y,2".as_bytes())?;
+        let mut edited = numeric.clone();
+        edited.set_cell(1, 1, Value::Text("z".to_string()))?;
+        let diff = numeric.diff(&edited, &["2"])?;
+        assert_eq!(diff.key_columns, vec!["2".to_string()]);
+        assert_eq!(diff.len(), 1);
+
+        let old = Reader::new().array_from_stream("id,v\n1,a\n2,b".as_bytes())?;
+        let new = Reader::new().array_from_stream("id,v\n1,a\n2,c".as_bytes())?;
+        assert_eq!(old.diff(&new, &["id"])?.len(), 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,478 @@
+//! Change tracking
+//!
+//! Containers remember original positions of rows and columns since load or the last
+//! mark_clean. Edits through methods of containers record original texts of cells they modify,
+//! thus tracking doesn't keep a copy of data. Cells are compared with original texts, thus a
+//! value which only changed its type such as a text "10" converted into a number 10 by
+//! set_limiter is not a modification. Editing rows directly through public fields is not
+//! tracked. Containers made by filter, transpose, group_by, join, pivot and melt start clean.
+
+use crate::sort::permute;
+use crate::vcont::{CellAlignType, VCont};
+use crate::virtual_array::VirtualArray;
+use crate::virtual_data::{Column, VirtualData};
+use crate::Value;
+use std::collections::{HashMap, HashSet};
+
+/// State of a cell, row or column compared with an original
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeState {
+    Clean,
+    Modified,
+    Inserted,
+}
+
+/// Changes since load or the last mark_clean
+///
+/// Inserted and modified items have current indices. Deleted rows have original indices and
+/// deleted columns have original names. Cells are (row, column) of modified original cells.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub inserted_rows: Vec<usize>,
+    pub modified_rows: Vec<usize>,
+    pub deleted_rows: Vec<usize>,
+    pub inserted_columns: Vec<usize>,
+    pub modified_columns: Vec<usize>,
+    pub deleted_columns: Vec<String>,
+    pub modified_cells: Vec<(usize, usize)>,
+}
+
+impl Changes {
+    /// Check if there is no change
+    pub fn is_empty(&self) -> bool {
+        self.inserted_rows.is_empty()
+            && self.modified_rows.is_empty()
+            && self.deleted_rows.is_empty()
+            && self.inserted_columns.is_empty()
+            && self.modified_columns.is_empty()
+            && self.deleted_columns.is_empty()
+            && self.modified_cells.is_empty()
+    }
+}
+
+/// Original indices of current rows and columns with original texts of edited cells
+///
+/// None is an inserted row or column. Rows or columns which were added by modifying fields
+/// directly are treated as inserted ones.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ChangeTracker {
+    rows: Vec<Option<usize>>,
+    columns: Vec<Option<usize>>,
+    row_count: usize,
+    column_names: Vec<String>,
+    /// Original texts of edited cells by original indices
+    originals: HashMap<(usize, usize), String>,
+    /// Count of edited cells which differ from original texts
+    modified: usize,
+    /// Whether rows or columns were inserted, deleted, moved or renamed
+    reshaped: bool,
+}
+
+impl ChangeTracker {
+    /// Create a tracker whose original is a current shape of a container
+    pub(crate) fn new(row_count: usize, columns: &[Column]) -> Self {
+        Self {
+            rows: (0..row_count).map(Some).collect(),
+            columns: (0..columns.len()).map(Some).collect(),
+            row_count,
+            column_names: columns.iter().map(|c| c.name.clone()).collect(),
+            originals: HashMap::new(),
+            modified: 0,
+            reshaped: false,
+        }
+    }
+
+    /// Record an edit of a cell with a previous value
+    ///
+    /// Cells of inserted rows or columns are not recorded.
+    pub(crate) fn set_cell(
+        &mut self,
+        row_index: usize,
+        column_index: usize,
+        previous: &Value,
+        value: &Value,
+    ) {
+        let (Some(row), Some(column)) =
+            (self.row_origin(row_index), self.column_origin(column_index))
+        else {
+            return;
+        };
+        let value = value.to_string();
+        match self.originals.get(&(row, column)) {
+            Some(original) => {
+                let was_modified = previous.to_string() != *original;
+                let is_modified = value != *original;
+                if was_modified && !is_modified {
+                    self.modified -= 1;
+                } else if !was_modified && is_modified {
+                    self.modified += 1;
+                }
+            }
+            None => {
+                let previous = previous.to_string();
+                if previous != value {
+                    self.originals.insert((row, column), previous);
+                    self.modified += 1;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn insert_row(&mut self, row_index: usize) {
+        if row_index <= self.rows.len() {
+            self.rows.insert(row_index, None);
+            self.reshaped = true;
+        }
+    }
+
+    pub(crate) fn delete_row(&mut self, row_index: usize) {
+        if row_index < self.rows.len() {
+            self.rows.remove(row_index);
+            self.reshaped = true;
+        }
+    }
+
+    pub(crate) fn move_row(&mut self, src_index: usize, target_index: usize) {
+        self.reshaped |= move_item(&mut self.rows, src_index, target_index);
+    }
+
+    /// Reorder rows with indices of original positions
+    pub(crate) fn permute_rows(&mut self, indices: &[usize]) {
+        if indices.len() == self.rows.len() {
+            self.rows = permute(std::mem::take(&mut self.rows), indices);
+            self.reshaped |= indices.iter().enumerate().any(|(i, index)| i != *index);
+        }
+    }
+
+    /// Keep rows whose indices are not removed
+    pub(crate) fn remove_rows(&mut self, removed: &HashSet<usize>) {
+        let mut index = 0;
+        self.rows.retain(|_| {
+            index += 1;
+            !removed.contains(&(index - 1))
+        });
+        self.reshaped |= !removed.is_empty();
+    }
+
+    pub(crate) fn clear_rows(&mut self) {
+        self.reshaped |= !self.rows.is_empty();
+        self.rows.clear();
+    }
+
+    pub(crate) fn insert_column(&mut self, column_index: usize) {
+        if column_index <= self.columns.len() {
+            self.columns.insert(column_index, None);
+            self.reshaped = true;
+        }
+    }
+
+    pub(crate) fn delete_column(&mut self, column_index: usize) {
+        if column_index < self.columns.len() {
+            self.columns.remove(column_index);
+            self.reshaped = true;
+        }
+    }
+
+    pub(crate) fn move_column(&mut self, src_index: usize, target_index: usize) {
+        self.reshaped |= move_item(&mut self.columns, src_index, target_index);
+    }
+
+    pub(crate) fn rename_column(&mut self) {
+        self.reshaped = true;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.reshaped |= !self.rows.is_empty() || !self.columns.is_empty();
+        self.rows.clear();
+        self.columns.clear();
+    }
+
+    fn row_origin(&self, row_index: usize) -> Option<usize> {
+        self.rows.get(row_index).copied().flatten()
+    }
+
+    fn column_origin(&self, column_index: usize) -> Option<usize> {
+        self.columns.get(column_index).copied().flatten()
+    }
+
+    /// Check if an original cell differs from its original text
+    fn is_modified(
+        &self,
+        data: &impl VCont,
+        x: usize,
+        y: usize,
+        row: usize,
+        column: usize,
+    ) -> bool {
+        match (self.originals.get(&(row, column)), data.get_cell(x, y)) {
+            (Some(original), Some(value)) => value.to_string() != *original,
+            _ => false,
+        }
+    }
+
+    fn cell_state(&self, data: &impl VCont, x: usize, y: usize) -> Option<ChangeState> {
+        data.get_cell(x, y)?;
+        let (Some(row), Some(column)) = (self.row_origin(x), self.column_origin(y)) else {
+            return Some(ChangeState::Inserted);
+        };
+        Some(modified_or_clean(self.is_modified(data, x, y, row, column)))
+    }
+
+    fn row_state(&self, data: &impl VCont, row_index: usize) -> Option<ChangeState> {
+        if row_index >= data.get_row_count() {
+            return None;
+        }
+        let Some(row) = self.row_origin(row_index) else {
+            return Some(ChangeState::Inserted);
+        };
+        let modified = (0..data.get_column_count()).any(|column| {
+            self.column_origin(column)
+                .is_some_and(|origin| self.is_modified(data, row_index, column, row, origin))
+        });
+        Some(modified_or_clean(modified))
+    }
+
+    fn column_state(
+        &self,
+        data: &impl VCont,
+        columns: &[Column],
+        column_index: usize,
+    ) -> Option<ChangeState> {
+        let name = &columns.get(column_index)?.name;
+        let Some(column) = self.column_origin(column_index) else {
+            return Some(ChangeState::Inserted);
+        };
+        let modified = self.column_names.get(column) != Some(name)
+            || (0..data.get_row_count()).any(|row| {
+                self.row_origin(row)
+                    .is_some_and(|origin| self.is_modified(data, row, column_index, origin, column))
+            });
+        Some(modified_or_clean(modified))
+    }
+
+    /// Get original indices which don't exist anymore
+    fn deleted(origins: &[Option<usize>], count: usize) -> Vec<usize> {
+        let mut exists = vec![false; count];
+        for origin in origins.iter().flatten() {
+            if let Some(flag) = exists.get_mut(*origin) {
+                *flag = true;
+            }
+        }
+        (0..count).filter(|index| !exists[*index]).collect()
+    }
+
+    /// Get current indices of original indices
+    fn positions(origins: &[Option<usize>]) -> HashMap<usize, usize> {
+        origins
+            .iter()
+            .enumerate()
+            .filter_map(|(index, origin)| origin.map(|origin| (origin, index)))
+            .collect()
+    }
+
+    fn changes(&self, data: &impl VCont, columns: &[Column]) -> Changes {
+        let mut changes = Changes::default();
+        let rows = Self::positions(&self.rows);
+        let column_positions = Self::positions(&self.columns);
+        for (&(row, column), original) in &self.originals {
+            let (Some(&x), Some(&y)) = (rows.get(&row), column_positions.get(&column)) else {
+                continue;
+            };
+            if data
+                .get_cell(x, y)
+                .is_some_and(|v| v.to_string() != *original)
+            {
+                changes.modified_cells.push((x, y));
+            }
+        }
+        changes.modified_cells.sort_unstable();
+
+        let mut modified_rows = changes
+            .modified_cells
+            .iter()
+            .map(|(row, _)| *row)
+            .collect::<Vec<_>>();
+        modified_rows.dedup();
+        changes.modified_rows = modified_rows;
+        changes.inserted_rows = (0..self.rows.len())
+            .filter(|row| self.rows[*row].is_none())
+            .collect();
+
+        let mut modified_columns = changes
+            .modified_cells
+            .iter()
+            .map(|(_, column)| *column)
+            .collect::<HashSet<_>>();
+        for (index, column) in columns.iter().enumerate() {
+            match self.column_origin(index) {
+                Some(origin) if self.column_names.get(origin) != Some(&column.name) => {
+                    modified_columns.insert(index);
+                }
+                Some(_) => (),
+                None => changes.inserted_columns.push(index),
+            }
+        }
+        changes.modified_columns = modified_columns.into_iter().collect();
+        changes.modified_columns.sort_unstable();
+
+        changes.deleted_rows = Self::deleted(&self.rows, self.row_count);
+        changes.deleted_columns = Self::deleted(&self.columns, self.column_names.len())
+            .into_iter()
+            .map(|index| self.column_names[index].clone())
+            .collect();
+        changes
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.reshaped || self.modified > 0
+    }
+
+    fn change_table(
+        &self,
+        data: &impl VCont,
+        columns: &[Column],
+        align_type: CellAlignType,
+    ) -> Vec<Vec<(String, ChangeState)>> {
+        data.get_string_table(align_type)
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| {
+                line.into_iter()
+                    .enumerate()
+                    .map(|(column, text)| {
+                        let state = if index == 0 {
+                            self.column_state(data, columns, column)
+                        } else {
+                            self.cell_state(data, index - 1, column)
+                        };
+                        (text, state.unwrap_or(ChangeState::Inserted))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn modified_or_clean(modified: bool) -> ChangeState {
+    if modified {
+        ChangeState::Modified
+    } else {
+        ChangeState::Clean
+    }
+}
+
+/// Move an item as move_row and move_column do and check if it moved
+fn move_item(items: &mut Vec<Option<usize>>, src_index: usize, target_index: usize) -> bool {
+    if src_index < items.len() && target_index < items.len() && src_index != target_index {
+        let item = items.remove(src_index);
+        items.insert(target_index, item);
+        return true;
+    }
+    false
+}
+
+impl VirtualData {
+    /// Forget changes and treat a current state as an original
+    pub fn mark_clean(&mut self) {
+        self.changes = ChangeTracker::new(self.get_row_count(), &self.columns);
+    }
+
+    /// Check if data has changes since load or the last mark_clean
+    ///
+    /// Moved rows or columns also make data dirty. This doesn't scan data, thus it is cheap to
+    /// call after every edit.
+    pub fn is_dirty(&self) -> bool {
+        self.changes.is_dirty()
+    }
+
+    /// Get changes since load or the last mark_clean
+    pub fn get_changes(&self) -> Changes {
+        self.changes.changes(self, &self.columns)
+    }
+
+    /// Get a state of a cell
+    ///
+    /// Cell of an inserted row or column is inserted.
+    pub fn get_cell_state(&self, x: usize, y: usize) -> Option<ChangeState> {
+        self.changes.cell_state(self, x, y)
+    }
+
+    /// Get a state of a row
+    ///
+    /// Row is modified when any original cell of a row is modified.
+    pub fn get_row_state(&self, row_index: usize) -> Option<ChangeState> {
+        self.changes.row_state(self, row_index)
+    }
+
+    /// Get a state of a column
+    ///
+    /// Column is modified when it is renamed or any original cell of a column is modified.
+    pub fn get_column_state(&self, column_index: usize) -> Option<ChangeState> {
+        self.changes.column_state(self, &self.columns, column_index)
+    }
+
+    /// Get an original index of a row
+    ///
+    /// This returns none for an inserted row.
+    pub fn get_original_row_index(&self, row_index: usize) -> Option<usize> {
+        self.changes.row_origin(row_index)
+    }
+
+    /// Get texts of get_string_table with states of cells and a header row of column states
+    pub fn get_change_table(&self, align_type: CellAlignType) -> Vec<Vec<(String, ChangeState)>> {
+        self.changes.change_table(self, &self.columns, align_type)
+    }
+}
+
+impl VirtualArray {
+    /// Forget changes and treat a current state as an original
+    pub fn mark_clean(&mut self) {
+        self.changes = ChangeTracker::new(self.get_row_count(), &self.columns);
+    }
+
+    /// Check if array has changes since load or the last mark_clean
+    ///
+    /// Moved rows or columns also make array dirty. This doesn't scan array, thus it is cheap
+    /// to call after every edit.
+    pub fn is_dirty(&self) -> bool {
+        self.changes.is_dirty()
+    }
+
+    /// Get changes since load or the last mark_clean
+    pub fn get_changes(&self) -> Changes {
+        self.changes.changes(self, &self.columns)
+    }
+
+    /// Get a state of a cell
+    ///
+    /// Cell of an inserted row or column is inserted.
+    pub fn get_cell_state(&self, x: usize, y: usize) -> Option<ChangeState> {
+        self.changes.cell_state(self, x, y)
+    }
+
+    /// Get a state of a row
+    ///
+    /// Row is modified when any original cell of a row is modified.
+    pub fn get_row_state(&self, row_index: usize) -> Option<ChangeState> {
+        self.changes.row_state(self, row_index)
+    }
+
+    /// Get a state of a column
+    ///
+    /// Column is modified when it is renamed or any original cell of a column is modified.
+    pub fn get_column_state(&self, column_index: usize) -> Option<ChangeState> {
+        self.changes.column_state(self, &self.columns, column_index)
+    }
+
+    /// Get an original index of a row
+    ///
+    /// This returns none for an inserted row.
+    pub fn get_original_row_index(&self, row_index: usize) -> Option<usize> {
+        self.changes.row_origin(row_index)
+    }
+
+    /// Get texts of get_string_table with states of cells and a header row of column states
+    pub fn get_change_table(&self, align_type: CellAlignType) -> Vec<Vec<(String, ChangeState)>> {
+        self.changes.change_table(self, &self.columns, align_type)
+    }
+}
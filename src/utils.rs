@@ -8,6 +8,15 @@ pub(crate) const ALPHABET: [&str; 26] = [
     "t", "u", "v", "w", "x", "y", "z",
 ];
 
+/// Quote a csv field which has a comma, a double quote or a line break
+pub(crate) fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
 /// Try getting csv row from split iterator
 ///
 /// This will return None when fails to get csv row
@@ -0,0 +1,134 @@
+//! Validation report of virtual data
+//!
+//! Validation collects every violation instead of failing on a first one.
+
+use crate::constraint::KeyKind;
+use crate::value::{Value, ValueType};
+
+/// Rule which a value or a row failed to satisfy
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationRule {
+    /// Value has a different type from limiter
+    Type(ValueType),
+    /// Value is empty while limiter is required
+    Required,
+    /// Value is not among limiter's variants
+    Variant,
+    /// Value doesn't match limiter's pattern
+    Pattern,
+    /// Value failed limiter's custom validator
+    Validator { name: String, message: String },
+    /// Key is duplicate or has an empty value
+    Key(KeyKind),
+    /// Row failed a row validator
+    RowValidator { name: String, message: String },
+    /// Key doesn't exist in referenced data of a foreign key
+    ForeignKey(String),
+}
+
+impl std::fmt::Display for ViolationRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Type(value_type) => write!(f, "type \"{}\"", value_type),
+            Self::Required => write!(f, "required"),
+            Self::Variant => write!(f, "variant"),
+            Self::Pattern => write!(f, "pattern"),
+            Self::Validator { name, message } => {
+                write!(f, "validator \"{}\" : {}", name, message)
+            }
+            Self::Key(kind) => write!(f, "{}", kind),
+            Self::RowValidator { name, message } => {
+                write!(f, "row validator \"{}\" : {}", name, message)
+            }
+            Self::ForeignKey(name) => write!(f, "foreign key \"{}\"", name),
+        }
+    }
+}
+
+/// A single violation of data
+///
+/// Cell violation has a single column and value, while row level violation can have multiple.
+#[derive(Clone, Debug)]
+pub struct Violation {
+    pub row: usize,
+    pub columns: Vec<String>,
+    pub values: Vec<Value>,
+    pub rule: ViolationRule,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Row {} ({}) = ({}) violates {}",
+            self.row,
+            self.columns.join(","),
+            self.values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            self.rule
+        )
+    }
+}
+
+/// Collection of violations
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if report has no violation
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Get count of violations
+    pub fn len(&self) -> usize {
+        self.violations.len()
+    }
+
+    /// Check if report is empty
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Add a violation
+    pub fn push(&mut self, violation: Violation) {
+        self.violations.push(violation);
+    }
+
+    /// Get iterator of violations
+    pub fn iter(&self) -> std::slice::Iter<'_, Violation> {
+        self.violations.iter()
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .violations
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// How to treat existing values which don't qualify a new limiter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimiterMode {
+    /// Fail without modifying data
+    Strict,
+    /// Replace unqualified values with limiter's default value
+    Overwrite,
+    /// Keep unqualified values untouched and report them
+    Collect,
+}
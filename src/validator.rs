@@ -0,0 +1,190 @@
+//! Custom validators for values
+//!
+//! Validator is a named closure which decides whether a value is acceptable. Validators are
+//! attached to a value limiter and evaluated wherever the limiter qualifies a value.
+
+use crate::error::{DcsvError, DcsvResult};
+use crate::value::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Signature of a custom validation function
+///
+/// Function returns an error message when a value is not valid.
+pub type ValidatorFn = dyn Fn(&Value) -> Result<(), String> + Send + Sync;
+
+/// Named custom validator
+///
+/// Name is used to refer to a validator from a schema.
+#[derive(Clone)]
+pub struct Validator {
+    name: String,
+    function: Arc<ValidatorFn>,
+}
+
+impl Validator {
+    /// Create a new validator with name and function
+    pub fn new<F>(name: &str, function: F) -> Self
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            function: Arc::new(function),
+        }
+    }
+
+    /// Get validator name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Validate a value
+    ///
+    /// This returns an error message from the validator function when value is not valid.
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        (self.function)(value)
+    }
+}
+
+impl std::fmt::Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Validator({})", self.name)
+    }
+}
+
+/// Registry of validators
+///
+/// Registry maps a name to a validator so that a schema can refer to a validator by its name.
+#[derive(Clone, Debug, Default)]
+pub struct ValidatorRegistry {
+    validators: HashMap<String, Validator>,
+}
+
+impl ValidatorRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a validator function with a name
+    ///
+    /// This overrides a validator with the same name.
+    pub fn register<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators
+            .insert(name.to_string(), Validator::new(name, function));
+    }
+
+    /// Add a validator
+    ///
+    /// This overrides a validator with the same name.
+    pub fn add(&mut self, validator: Validator) {
+        self.validators
+            .insert(validator.get_name().to_string(), validator);
+    }
+
+    /// Get a validator by name
+    pub fn get(&self, name: &str) -> Option<&Validator> {
+        self.validators.get(name)
+    }
+
+    /// Get a validator by name or return error if it is not registered
+    pub fn try_get(&self, name: &str) -> DcsvResult<&Validator> {
+        self.get(name).ok_or_else(|| {
+            DcsvError::InvalidLimiter(format!("Validator \"{}\" is not registered", name))
+        })
+    }
+}
+
+/// Signature of a row validation function
+///
+/// Function receives values of validator's columns in declared order and returns an error message
+/// when values are not valid.
+pub type RowValidatorFn = dyn Fn(&[&Value]) -> Result<(), String> + Send + Sync;
+
+/// Named validator over multiple columns of a row
+#[derive(Clone)]
+pub struct RowValidator {
+    name: String,
+    columns: Vec<String>,
+    function: Arc<RowValidatorFn>,
+}
+
+impl RowValidator {
+    /// Create a new row validator with name, columns and function
+    pub fn new<F>(name: &str, columns: &[impl AsRef<str>], function: F) -> Self
+    where
+        F: Fn(&[&Value]) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.as_ref().to_string()).collect(),
+            function: Arc::new(function),
+        }
+    }
+
+    /// Get validator name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get column names of validator
+    pub fn get_columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Check if validator includes a column
+    pub fn has_column(&self, name: &str) -> bool {
+        self.columns.iter().any(|c| c == name)
+    }
+
+    /// Validate values of columns
+    pub fn validate(&self, values: &[&Value]) -> Result<(), String> {
+        (self.function)(values)
+    }
+
+    /// Rename a column of validator
+    pub(crate) fn rename_column(&mut self, name: &str, new_name: &str) {
+        for col in &mut self.columns {
+            if col == name {
+                *col = new_name.to_string();
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for RowValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RowValidator({} : {})",
+            self.name,
+            self.columns.join(",")
+        )
+    }
+}
+
+/// Violation of a row validator
+#[derive(Clone, Debug)]
+pub struct RowViolation {
+    pub row: usize,
+    pub validator: String,
+    pub columns: Vec<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for RowViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Row {} violates \"{}\" over ({}) : {}",
+            self.row,
+            self.validator,
+            self.columns.join(","),
+            self.message
+        )
+    }
+}
@@ -3,12 +3,19 @@
 //! Value can be either number or text.
 
 use crate::error::{DcsvError, DcsvResult};
+use crate::validation::ViolationRule;
+use crate::validator::{Validator, ValidatorRegistry};
 use regex::Regex;
 use std::{fmt::Display, str::FromStr};
 
-/// Length of limiter's attributes
+/// Length of limiter's base attributes which are type, default, variant and pattern
 pub const LIMITER_ATTRIBUTE_LEN: usize = 4;
 
+/// Length of limiter's attributes including validator and required flag
+///
+/// Attributes after pattern are optional for compatibility with older schemas.
+pub const EXTENDED_LIMITER_ATTRIBUTE_LEN: usize = 6;
+
 /// Basic component of virtual data
 ///
 /// Value can be either number or text.
@@ -17,7 +24,7 @@ pub const LIMITER_ATTRIBUTE_LEN: usize = 4;
 ///
 /// Dcsv doesn't support float type because float can change the "original" source while
 /// overriding. Since dcsv's goal is about safe manipulation of csv value, float is not appropriate.
-#[derive(Clone, Eq, PartialEq, PartialOrd, Debug)]
+#[derive(Clone, Eq, PartialEq, PartialOrd, Hash, Debug)]
 pub enum Value {
     Number(isize),
     Text(String),
@@ -65,6 +72,16 @@ impl Value {
         }
     }
 
+    /// Check if value is empty
+    ///
+    /// Only an empty text is empty. Number is never empty.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Number(_) => false,
+            Self::Text(text) => text.is_empty(),
+        }
+    }
+
     /// Return unicode_width of the value
     pub fn get_width(&self) -> usize {
         match self {
@@ -100,11 +117,13 @@ impl std::fmt::Display for Value {
 // because some limiters are mutually exclusive.
 /// Limiter that costraints which data that Value can hold
 ///
-/// VaulueLimiter has four properties
+/// VaulueLimiter has six properties
 /// - type ( Eitehr number or text )
 /// - default value
 /// - variants ( Range of values )
 /// - pattern ( Regex pattern )
+/// - validator ( Custom validation closure )
+/// - required ( Whether empty value is rejected )
 #[derive(Default, Clone, Debug)]
 pub struct ValueLimiter {
     // Allowed variant
@@ -112,6 +131,24 @@ pub struct ValueLimiter {
     default: Option<Value>,
     variant: Option<Vec<Value>>,
     pattern: Option<Regex>, // -> This better be a regex
+    validator: Option<Validator>,
+    required: bool,
+}
+
+/// Limiters are equal when they have same rules
+///
+/// Patterns are compared by their sources and validators are compared by their names.
+impl PartialEq for ValueLimiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.value_type == other.value_type
+            && self.default == other.default
+            && self.variant == other.variant
+            && self.pattern.as_ref().map(|p| p.as_str())
+                == other.pattern.as_ref().map(|p| p.as_str())
+            && self.validator.as_ref().map(|v| v.get_name())
+                == other.validator.as_ref().map(|v| v.get_name())
+            && self.required == other.required
+    }
 }
 
 impl Display for ValueLimiter {
@@ -120,6 +157,12 @@ impl Display for ValueLimiter {
         if let Some(var) = &self.variant {
             writeln!(f, "default value : {:?}", &var)?;
         }
+        if let Some(var) = &self.validator {
+            writeln!(f, "validator : {}", var.get_name())?;
+        }
+        if self.required {
+            writeln!(f, "required : true")?;
+        }
         if let Some(var) = &self.variant {
             write!(f, "variants : {:?}", var)
         } else if let Some(var) = &self.pattern {
@@ -158,30 +201,79 @@ impl ValueLimiter {
     }
 
     /// Check if value qualifies
+    ///
+    /// Custom validator is evaluated after type, variant and pattern qualification.
     pub fn qualify(&self, value: &Value) -> bool {
+        self.check(value).is_ok()
+    }
+
+    /// Check if value qualifies and return a rule which value failed
+    ///
+    /// Rules are checked in an order of type, required, variant or pattern and validator.
+    pub fn check(&self, value: &Value) -> Result<(), ViolationRule> {
         if value.get_type() != self.get_type() {
-            return false;
+            return Err(ViolationRule::Type(self.get_type()));
         }
-        match value {
-            Value::Number(num) => {
-                if let Some(variant) = self.variant.as_ref() {
-                    variant.contains(value)
-                } else if let Some(pattern) = self.pattern.as_ref() {
-                    pattern.is_match(&num.to_string())
-                } else {
-                    true
-                }
+        if self.required && value.is_empty() {
+            return Err(ViolationRule::Required);
+        }
+        if let Some(variant) = self.variant.as_ref() {
+            if !variant.contains(value) {
+                return Err(ViolationRule::Variant);
             }
-            Value::Text(text) => {
-                if let Some(variant) = self.variant.as_ref() {
-                    variant.contains(value)
-                } else if let Some(pattern) = self.pattern.as_ref() {
-                    pattern.is_match(text)
-                } else {
-                    true
-                }
+        } else if let Some(pattern) = self.pattern.as_ref() {
+            let matched = match value {
+                Value::Number(num) => pattern.is_match(&num.to_string()),
+                Value::Text(text) => pattern.is_match(text),
+            };
+            if !matched {
+                return Err(ViolationRule::Pattern);
             }
         }
+        if let Err(message) = self.validate(value) {
+            // It is safe to unwrap because only validator can fail validation
+            return Err(ViolationRule::Validator {
+                name: self.validator.as_ref().unwrap().get_name().to_string(),
+                message,
+            });
+        }
+        Ok(())
+    }
+
+    /// Convert a value to limiter's type and check if it qualifies
+    ///
+    /// Empty value is converted to a non empty number, thus required is checked before
+    /// conversion.
+    pub(crate) fn conform(&self, value: &Value) -> Result<Value, ViolationRule> {
+        if self.required && value.is_empty() {
+            return Err(ViolationRule::Required);
+        }
+        // Check if value can be converted at most
+        let converted = match self.is_convertible(value) {
+            Some(ttype) => Value::from_str(&value.to_string(), ttype)
+                .map_err(|_| ViolationRule::Type(ttype))?,
+            None => value.clone(),
+        };
+        self.check(&converted)?;
+        Ok(converted)
+    }
+
+    /// Get a default value or an empty value of limiter's type
+    pub(crate) fn get_default_or_empty(&self) -> Value {
+        self.default
+            .clone()
+            .unwrap_or_else(|| Value::empty(self.value_type))
+    }
+
+    /// Run custom validator against a value
+    ///
+    /// This returns validator's error message when value is not valid. Limiter without a
+    /// validator always succeeds.
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        match self.validator.as_ref() {
+            Some(validator) => validator.validate(value),
+            None => Ok(()),
+        }
     }
 
     /// Create value limiter from attributes
@@ -191,9 +283,26 @@ impl ValueLimiter {
     /// - Default
     /// - Variant
     /// - Pattern
+    /// - Validator ( Optional )
+    /// - Required ( Optional, either "true" or "false" )
+    ///
+    /// Older schemas can omit trailing validator and required attributes. Use
+    /// from_line_with_registry to resolve validator names.
     pub fn from_line(attributes: &[impl AsRef<str>]) -> DcsvResult<Self> {
+        Self::from_line_with_registry(attributes, &ValidatorRegistry::default())
+    }
+
+    /// Create value limiter from attributes and resolve validator name from registry
+    ///
+    /// This fails when validator name is not registered in a given registry.
+    pub fn from_line_with_registry(
+        attributes: &[impl AsRef<str>],
+        registry: &ValidatorRegistry,
+    ) -> DcsvResult<Self> {
         let attributes: Vec<&str> = attributes.iter().map(|s| s.as_ref()).collect();
-        if attributes.len() != LIMITER_ATTRIBUTE_LEN {
+        if attributes.len() < LIMITER_ATTRIBUTE_LEN
+            || attributes.len() > EXTENDED_LIMITER_ATTRIBUTE_LEN
+        {
             return Err(DcsvError::InvalidRowData(format!(
                 "Schema row has insufficient columns \n= {:?}",
                 attributes
@@ -204,7 +313,14 @@ impl ValueLimiter {
         let default = attributes[1];
         let variants = attributes[2];
         let pattern = attributes[3];
+        let validator = attributes.get(4).copied().unwrap_or_default();
+        let required = attributes.get(5).copied().unwrap_or_default();
         limiter.set_type(vt);
+        limiter.set_required(parse_flag(required)?);
+
+        if !validator.is_empty() {
+            limiter.set_validator(registry.try_get(validator)?.clone());
+        }
 
         // Default value is necessary for complicated limiter
         if !default.is_empty() {
@@ -219,10 +335,10 @@ impl ValueLimiter {
                 limiter.set_variant(default, &values)?;
             } else if !pattern.is_empty() {
                 // Do patterns
-                limiter.set_pattern(
-                    default,
-                    Regex::new(pattern).expect("Failed to create pattern"),
-                )?;
+                let pattern = Regex::new(pattern).map_err(|err| {
+                    DcsvError::InvalidLimiter(format!("Invalid pattern \"{}\" : {}", pattern, err))
+                })?;
+                limiter.set_pattern(default, pattern)?;
             } else {
                 limiter.default = Some(default);
             }
@@ -285,12 +401,60 @@ impl ValueLimiter {
         self.pattern.replace(pattern);
         Ok(())
     }
+
+    /// Set pattern without default value
+    ///
+    /// This is for schemas which don't carry a default value. Column's default value might not
+    /// match the pattern.
+    pub(crate) fn set_pattern_without_default(&mut self, pattern: Regex) {
+        self.pattern.replace(pattern);
+    }
+
+    /// Get custom validator
+    pub fn get_validator(&self) -> Option<&Validator> {
+        self.validator.as_ref()
+    }
+
+    /// Set custom validator
+    pub fn set_validator(&mut self, validator: Validator) {
+        self.validator.replace(validator);
+    }
+
+    /// Remove custom validator and return it
+    pub fn remove_validator(&mut self) -> Option<Validator> {
+        self.validator.take()
+    }
+
+    /// Check if limiter rejects empty value
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Set whether limiter rejects empty value
+    pub fn set_required(&mut self, required: bool) {
+        self.required = required;
+    }
+}
+
+/// Parse boolean flag of a schema attribute
+///
+/// Empty attribute is evaluated to false.
+fn parse_flag(src: &str) -> DcsvResult<bool> {
+    match src.trim().to_lowercase().as_str() {
+        "" | "false" => Ok(false),
+        "true" => Ok(true),
+        _ => Err(DcsvError::InvalidLimiter(format!(
+            "\"{}\" is not a valid flag. Flag should be either true or false",
+            src
+        ))),
+    }
 }
 
 /// Type of a value
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub enum ValueType {
     Number,
+    #[default]
     Text,
 }
 
@@ -321,9 +485,3 @@ impl std::str::FromStr for ValueType {
         }
     }
 }
-
-impl Default for ValueType {
-    fn default() -> Self {
-        Self::Text
-    }
-}
@@ -1,6 +1,8 @@
 //! VCont is a generic trait for various virtual csv structs
 
-use crate::{DcsvResult, Value};
+use crate::search::{compile, Scope};
+use crate::sort::row_order;
+use crate::{Comparator, DcsvResult, SortKey, Value};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CellAlignType {
@@ -20,6 +22,29 @@ pub trait VCont {
     /// Move a given row to a target row index
     fn move_row(&mut self, src_index: usize, target_index: usize) -> DcsvResult<()>;
 
+    /// Sort rows by sort keys
+    ///
+    /// Sort is stable. Column informations are not affected.
+    ///
+    /// Default implementation moves rows one by one with move_row, thus implementors are
+    /// encouraged to override it.
+    fn sort_rows(&mut self, keys: &[SortKey]) -> DcsvResult<()> {
+        let indices = row_order(self, keys)?;
+
+        // Original row indices in current order
+        let mut order = (0..indices.len()).collect::<Vec<_>>();
+        for (target, index) in indices.into_iter().enumerate() {
+            // It is safe to unwrap because order has every row index
+            let src = order.iter().position(|i| *i == index).unwrap();
+            if src != target {
+                self.move_row(src, target)?;
+                order.remove(src);
+                order.insert(target, index);
+            }
+        }
+        Ok(())
+    }
+
     /// Move a given column to target column index
     fn move_column(&mut self, src_index: usize, target_index: usize) -> DcsvResult<()>;
 
@@ -75,4 +100,87 @@ pub trait VCont {
 
     /// Get table as raw string vectors of vectors
     fn get_string_table(&self, align_type: CellAlignType) -> Vec<Vec<String>>;
+
+    /// Get a minimum value of a column by comparator
+    ///
+    /// Empty values are ignored. First value wins among equal values.
+    fn get_min_value(&self, column: usize, comparator: Comparator) -> Option<&Value> {
+        (0..self.get_row_count())
+            .filter_map(|row| self.get_cell(row, column))
+            .filter(|v| !v.is_empty())
+            .reduce(|min, v| {
+                if comparator.compare(v, min).is_lt() {
+                    v
+                } else {
+                    min
+                }
+            })
+    }
+
+    /// Get a maximum value of a column by comparator
+    ///
+    /// Empty values are ignored. First value wins among equal values.
+    fn get_max_value(&self, column: usize, comparator: Comparator) -> Option<&Value> {
+        (0..self.get_row_count())
+            .filter_map(|row| self.get_cell(row, column))
+            .filter(|v| !v.is_empty())
+            .reduce(|max, v| {
+                if comparator.compare(v, max).is_gt() {
+                    v
+                } else {
+                    max
+                }
+            })
+    }
+
+    /// Find cells whose text forms match a regex pattern
+    ///
+    /// Coordinates are (row, column) in row order.
+    fn find(&self, pattern: &str, scope: &Scope) -> DcsvResult<Vec<(usize, usize)>> {
+        let regex = compile(pattern)?;
+        Ok(scope
+            .cells(self.get_row_count(), self.get_column_count())?
+            .into_iter()
+            .filter(|(row, column)| {
+                self.get_cell(*row, *column)
+                    .map(|v| regex.is_match(&v.to_string()))
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    /// Apply edits as a transaction
+    ///
+    /// Edits are applied to a copy of container which replaces the original only when closure
+    /// succeeds. Thus either all edits are applied or none of them.
+    ///
+    /// Default implementation copies a whole container, which takes time and memory in
+    /// proportion to a size of container regardless of edits. History overrides it to revert
+    /// recorded edits in place instead.
+    ///
+    /// # Usage
+    ///
+    /// ```rust
+    /// use dcsv::{Reader, VCont, Value};
+    ///
+    /// let mut data = Reader::new()
+    ///     .data_from_stream("a,b\n1,2".as_bytes())
+    ///     .expect("Failed to read data");
+    /// let result = data.transaction(|tx| {
+    ///     tx.set_cell(0, 0, Value::Text("3".to_string()))?;
+    ///     tx.set_cell(5, 0, Value::Text("4".to_string()))
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(data.get_cell(0, 0).unwrap().to_string(), "1");
+    /// ```
+    fn transaction<R, F>(&mut self, edit: F) -> DcsvResult<R>
+    where
+        Self: Sized + Clone,
+        F: FnOnce(&mut Self) -> DcsvResult<R>,
+    {
+        let mut tx = self.clone();
+        let result = edit(&mut tx)?;
+        *self = tx;
+        Ok(result)
+    }
 }
@@ -3,19 +3,29 @@
 use crate::CellAlignType;
 use unicode_width::UnicodeWidthStr;
 
-use crate::{meta::Meta, vcont::VCont, Column, DcsvError, DcsvResult, Value};
+use crate::formula::Formulas;
+use crate::query::Query;
+use crate::reader::make_arbitrary_column;
+use crate::sort::{inverse, permute, sorted_indices};
+use crate::tracking::ChangeTracker;
+use crate::{meta::Meta, vcont::VCont, Column, DcsvError, DcsvResult, SortKey, Value};
 use std::cmp::Ordering;
+use std::collections::HashMap;
 
 /// Virtual array which contains csv information in a form of arrays.
 ///
 /// - VirtualArray holds row information as vectors. Therefore indexing is generally faster than virtual data struct.
 /// - VirtualArray allows duplicate columns
 /// - VirtualArray doesn't allow limiters.
+/// - VirtualArray allows spreadsheet formulas with A1 references.
+/// - VirtualArray tracks changed cells, rows and columns since load.
 #[derive(Clone)]
 pub struct VirtualArray {
     pub metas: Vec<Meta>,
     pub columns: Vec<Column>,
     pub rows: Vec<Vec<Value>>,
+    pub(crate) formulas: Formulas,
+    pub(crate) changes: ChangeTracker,
 }
 
 impl Default for VirtualArray {
@@ -30,6 +40,8 @@ impl VCont for VirtualArray {
             metas: vec![],
             columns: vec![],
             rows: vec![],
+            formulas: Formulas::default(),
+            changes: ChangeTracker::default(),
         }
     }
 
@@ -44,6 +56,8 @@ impl VCont for VirtualArray {
     fn drop_data(&mut self) {
         self.columns.clear();
         self.rows.clear();
+        self.formulas.clear();
+        self.changes.clear();
     }
 
     /// Rename a column
@@ -51,6 +65,7 @@ impl VCont for VirtualArray {
     /// This will simply change the name of the column and doesn't affect rows.
     fn rename_column(&mut self, column_index: usize, new_name: &str) -> DcsvResult<()> {
         self.columns[column_index].name = new_name.to_owned();
+        self.changes.rename_column();
         Ok(())
     }
 
@@ -62,10 +77,19 @@ impl VCont for VirtualArray {
             return Err(DcsvError::OutOfRangeError);
         }
 
-        for row in &mut self.rows {
-            self.metas[column_index].update_width_from_value(&value);
+        self.metas[column_index].update_width_from_value(&value);
+        for (index, row) in self.rows.iter_mut().enumerate() {
+            self.changes
+                .set_cell(index, column_index, &row[column_index], &value);
             row[column_index] = value.clone();
         }
+        let cells = (0..self.get_row_count())
+            .map(|row| (row, column_index))
+            .collect::<Vec<_>>();
+        for cell in &cells {
+            self.formulas.remove(cell);
+        }
+        self.recalculate_from(&cells);
         Ok(())
     }
 
@@ -86,12 +110,19 @@ impl VCont for VirtualArray {
         // It is safe to unwrap because row_number
         // was validated by is_valid_cell_coordinate method.
         let row = &mut self.rows[row_index];
+        let mut cells = vec![];
         for (idx, v) in values.iter().enumerate() {
             if let Some(new_value) = v {
                 self.metas[idx].update_width_from_value(new_value);
+                self.changes.set_cell(row_index, idx, &row[idx], new_value);
                 row[idx] = new_value.clone();
+                cells.push((row_index, idx));
             }
         }
+        for cell in &cells {
+            self.formulas.remove(cell);
+        }
+        self.recalculate_from(&cells);
 
         Ok(())
     }
@@ -116,6 +147,11 @@ impl VCont for VirtualArray {
         for (col, value) in self.metas.iter_mut().zip(self.rows[row_index].iter()) {
             col.update_width_from_value(value)
         }
+        self.changes.insert_row(row_index);
+        self.remap_formulas(
+            |row| Some(if row >= row_index { row + 1 } else { row }),
+            Some,
+        );
         Ok(())
     }
 
@@ -128,6 +164,15 @@ impl VCont for VirtualArray {
             return false;
         }
         let removed = self.rows.remove(row_index);
+        self.changes.delete_row(row_index);
+        self.remap_formulas(
+            |row| match row.cmp(&row_index) {
+                Ordering::Less => Some(row),
+                Ordering::Equal => None,
+                Ordering::Greater => Some(row - 1),
+            },
+            Some,
+        );
 
         let to_be_updated_colum_index = removed
             .iter()
@@ -172,31 +217,23 @@ impl VCont for VirtualArray {
         self.metas.insert(column_index, meta);
         self.columns
             .insert(column_index, Column::empty(column_name));
+        self.changes.insert_column(column_index);
         for row in &mut self.rows {
             row.insert(column_index, Value::Text(String::new()));
         }
+        self.remap_formulas(Some, |column| {
+            Some(if column >= column_index {
+                column + 1
+            } else {
+                column
+            })
+        });
         Ok(())
     }
 
     /// Delete a column with given column index
     fn delete_column(&mut self, column_index: usize) -> DcsvResult<()> {
-        if !self.is_valid_cell_coordinate(0, column_index) {
-            return Err(DcsvError::OutOfRangeError);
-        }
-
-        for row in &mut self.rows {
-            row.remove(column_index);
-        }
-
-        self.metas.remove(column_index);
-        self.columns.remove(column_index);
-
-        // If column is empty, drop all rows
-        if self.get_column_count() == 0 {
-            self.rows = vec![];
-        }
-
-        Ok(())
+        self.take_column(column_index).map(|_| ())
     }
 
     fn get_cell(&self, x: usize, y: usize) -> Option<&Value> {
@@ -213,7 +250,10 @@ impl VCont for VirtualArray {
             return Err(DcsvError::OutOfRangeError);
         }
         self.metas[y].update_width_from_value(&value);
+        self.changes.set_cell(x, y, &self.rows[x][y], &value);
         self.rows[x][y] = value;
+        self.formulas.remove(&(x, y));
+        self.recalculate_from(&[(x, y)]);
         Ok(())
     }
 
@@ -259,6 +299,25 @@ impl VCont for VirtualArray {
             }
             Ordering::Equal => (),
         }
+        // Values move with a column
+        if src_index != target_index {
+            for row in &mut self.rows {
+                let value = row.remove(src_index);
+                row.insert(target_index, value);
+            }
+        }
+        self.changes.move_column(src_index, target_index);
+        self.remap_formulas(Some, |column| {
+            Some(if column == src_index {
+                target_index
+            } else if src_index < column && column <= target_index {
+                column - 1
+            } else if target_index <= column && column < src_index {
+                column + 1
+            } else {
+                column
+            })
+        });
         Ok(())
     }
 
@@ -301,6 +360,35 @@ impl VCont for VirtualArray {
             }
             Ordering::Equal => (),
         }
+        self.remap_formulas(
+            |row| {
+                Some(if row == src_index {
+                    target_index
+                } else if src_index < row && row <= target_index {
+                    row - 1
+                } else if target_index <= row && row < src_index {
+                    row + 1
+                } else {
+                    row
+                })
+            },
+            Some,
+        );
+        self.changes.move_row(src_index, target_index);
+        Ok(())
+    }
+
+    fn sort_rows(&mut self, keys: &[SortKey]) -> DcsvResult<()> {
+        if keys.iter().any(|k| k.column >= self.get_column_count()) {
+            return Err(DcsvError::OutOfRangeError);
+        }
+        let values = self
+            .rows
+            .iter()
+            .map(|row| keys.iter().map(|k| &row[k.column]).collect())
+            .collect::<Vec<_>>();
+        let indices = sorted_indices(keys, &values);
+        self.permute_rows(&indices);
         Ok(())
     }
 
@@ -315,18 +403,33 @@ impl VCont for VirtualArray {
         }
         for (idx, v) in values.iter().enumerate() {
             self.metas[idx].update_width_from_value(v);
+            self.changes
+                .set_cell(row_index, idx, &self.rows[row_index][idx], v);
         }
         self.rows[row_index] = values.to_vec();
+        let cells = (0..values.len())
+            .map(|column| (row_index, column))
+            .collect::<Vec<_>>();
+        for cell in &cells {
+            self.formulas.remove(cell);
+        }
+        self.recalculate_from(&cells);
 
         Ok(())
     }
 
+    /// Apply a function to every value
+    ///
+    /// Formulas are recomputed afterwards, thus values of formula cells are not affected.
     fn apply_all<F: FnMut(&mut Value)>(&mut self, mut f: F) {
-        for row in &mut self.rows {
-            for value in row {
-                f(value)
+        for (x, row) in self.rows.iter_mut().enumerate() {
+            for (y, value) in row.iter_mut().enumerate() {
+                let previous = value.clone();
+                f(value);
+                self.changes.set_cell(x, y, &previous, value);
             }
         }
+        self.recalculate();
     }
 
     fn update_width_global(&mut self) {
@@ -434,6 +537,193 @@ impl VirtualArray {
             .into_iter())
     }
 
+    /// Get indices of rows which match a query
+    ///
+    /// Duplicate column name refers to a first column with the name.
+    pub fn query_rows(&self, query: &Query) -> DcsvResult<Vec<usize>> {
+        let mut indices = HashMap::new();
+        for column in query.get_columns() {
+            let index = self
+                .columns
+                .iter()
+                .position(|c| c.name == column)
+                .ok_or_else(|| {
+                    DcsvError::InvalidColumn(format!(
+                        "Query refers to a non existent column \"{}\"",
+                        column
+                    ))
+                })?;
+            indices.insert(column, index);
+        }
+        Ok(self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| query.matches(|column| indices.get(column).map(|i| &row[*i])))
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// Get a new virtual array with rows which match a query
+    pub fn filter(&self, query: &Query) -> DcsvResult<Self> {
+        let rows = self
+            .query_rows(query)?
+            .into_iter()
+            .map(|index| self.rows[index].clone())
+            .collect();
+        let mut array = Self {
+            metas: self
+                .columns
+                .iter()
+                .map(|c| {
+                    let mut meta = Meta::new();
+                    meta.set_width(UnicodeWidthStr::width(c.name.as_str()));
+                    meta
+                })
+                .collect(),
+            columns: self.columns.clone(),
+            rows,
+            formulas: Formulas::default(),
+            changes: ChangeTracker::default(),
+        };
+        array.update_width_global();
+        array.mark_clean();
+        Ok(array)
+    }
+
+    /// Get a new virtual array with rows and columns swapped
+    ///
+    /// Header becomes a first column. When header is true, first column becomes a new header and
+    /// other columns become rows. Otherwise every column becomes a row with arbitrary header
+    /// names. Transposing twice with header returns an identical array.
+    pub fn transpose(&self, header: bool) -> Self {
+        let skip = usize::from(header && !self.columns.is_empty());
+        let names = if skip == 1 {
+            std::iter::once(self.columns[0].name.clone())
+                .chain(self.rows.iter().map(|row| row[0].to_string()))
+                .collect()
+        } else {
+            make_arbitrary_column(self.get_row_count() + 1)
+        };
+        let rows = self
+            .columns
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(index, column)| {
+                std::iter::once(Value::Text(column.name.clone()))
+                    .chain(self.rows.iter().map(|row| row[index].clone()))
+                    .collect()
+            })
+            .collect();
+        let mut array = Self {
+            metas: names
+                .iter()
+                .map(|name| {
+                    let mut meta = Meta::new();
+                    meta.set_width(UnicodeWidthStr::width(name.as_str()));
+                    meta
+                })
+                .collect(),
+            columns: names.iter().map(|name| Column::empty(name)).collect(),
+            rows,
+            formulas: Formulas::default(),
+            changes: ChangeTracker::default(),
+        };
+        array.update_width_global();
+        array.mark_clean();
+        array
+    }
+
+    /// Delete a column and get it back with its values
+    pub(crate) fn take_column(&mut self, column_index: usize) -> DcsvResult<RemovedArrayColumn> {
+        if !self.is_valid_cell_coordinate(0, column_index) {
+            return Err(DcsvError::OutOfRangeError);
+        }
+
+        let values = self
+            .rows
+            .iter_mut()
+            .map(|row| row.remove(column_index))
+            .collect();
+        let meta = self.metas.remove(column_index);
+        let column = self.columns.remove(column_index);
+        self.changes.delete_column(column_index);
+
+        // If column is empty, drop all rows
+        if self.get_column_count() == 0 {
+            self.rows = vec![];
+            self.changes.clear_rows();
+            self.formulas.clear();
+        }
+        self.remap_formulas(Some, |column| match column.cmp(&column_index) {
+            Ordering::Less => Some(column),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(column - 1),
+        });
+
+        Ok(RemovedArrayColumn {
+            column,
+            meta,
+            values,
+        })
+    }
+
+    /// Insert a column which was taken by take_column
+    ///
+    /// Rows are created again when a column was the last one.
+    pub(crate) fn restore_column(&mut self, column_index: usize, removed: RemovedArrayColumn) {
+        if self.columns.is_empty() {
+            self.rows = vec![vec![]; removed.values.len()];
+            for row in 0..self.rows.len() {
+                self.changes.insert_row(row);
+            }
+        }
+        for (row, value) in self.rows.iter_mut().zip(removed.values) {
+            row.insert(column_index, value);
+        }
+        self.metas.insert(column_index, removed.meta);
+        self.columns.insert(column_index, removed.column);
+        self.changes.insert_column(column_index);
+        self.remap_formulas(Some, |column| {
+            Some(if column >= column_index {
+                column + 1
+            } else {
+                column
+            })
+        });
+    }
+
+    /// Reorder rows with indices of original positions
+    ///
+    /// Formulas move with their rows.
+    pub(crate) fn permute_rows(&mut self, indices: &[usize]) {
+        self.rows = permute(std::mem::take(&mut self.rows), indices);
+        self.changes.permute_rows(indices);
+        let positions = inverse(indices);
+        self.remap_formulas(|row| Some(positions.get(row).copied().unwrap_or(row)), Some);
+    }
+
+    /// Write values of cells as they were
+    ///
+    /// Formulas are kept and recomputed. Array is not modified when any cell is out of range.
+    pub(crate) fn restore_cells(&mut self, cells: &[(usize, usize, Value)]) -> DcsvResult<()> {
+        if cells
+            .iter()
+            .any(|(x, y, _)| !self.is_valid_cell_coordinate(*x, *y))
+        {
+            return Err(DcsvError::OutOfRangeError);
+        }
+        for (x, y, value) in cells {
+            self.metas[*y].update_width_from_value(value);
+            self.changes.set_cell(*x, *y, &self.rows[*x][*y], value);
+            self.rows[*x][*y] = value.clone();
+        }
+        let cells = cells.iter().map(|(x, y, _)| (*x, *y)).collect::<Vec<_>>();
+        self.recompute_cells(&cells);
+        Ok(())
+    }
+
     /// Check if cell coordinate is not out of range
     fn is_valid_cell_coordinate(&self, x: usize, y: usize) -> bool {
         if x < self.get_row_count() && y < self.get_column_count() {
@@ -459,6 +749,16 @@ impl VirtualArray {
     }
 }
 
+/// Column which is removed from a virtual array with its values
+///
+/// History keeps it to restore a deleted column.
+#[derive(Clone)]
+pub struct RemovedArrayColumn {
+    column: Column,
+    meta: Meta,
+    values: Vec<Value>,
+}
+
 /// to_string implementation for virtual array
 ///
 /// This returns csv value string
@@ -2,27 +2,44 @@
 
 use unicode_width::UnicodeWidthStr;
 
+use crate::computed::DerivedColumn;
+use crate::constraint::{text_key, DanglingReference, ForeignKey, KeyConstraint, KeyKind};
 use crate::error::{DcsvError, DcsvResult};
 use crate::meta::Meta;
+use crate::query::Query;
+use crate::reader::make_arbitrary_column;
+use crate::sort::{permute, sorted_indices, SortKey};
+use crate::tracking::ChangeTracker;
+use crate::validation::{LimiterMode, ValidationReport, Violation, ViolationRule};
+use crate::validator::{RowValidator, RowViolation, Validator};
 use crate::value::{Value, ValueLimiter, ValueType};
 use crate::vcont::VCont;
 use crate::CellAlignType;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-
-/// Header for csv schema
-pub const SCHEMA_HEADER: &str = "column,type,default,variant,pattern";
+use std::collections::{HashMap, HashSet};
 
 /// Virtual data struct which contains csv information
 ///
 /// - VirtualData holds row information as hashmap. Therefore modifying data( cell, row or column ) is generally faster than virtual array struct.
 /// - VirtualData cannot have duplicate column name due to previous hashmap implementaiton
 /// - VirtualData allows limiters to confine csv value's possible states.
+/// - VirtualData allows unique and primary key constraints over columns.
+/// - VirtualData allows row validators which span multiple columns.
+/// - VirtualData allows foreign keys which refer to another virtual data.
+/// - VirtualData tracks changed cells, rows and columns since load.
+///
+/// Key constraints, row validators and enforced foreign keys are enforced by VirtualData's
+/// methods. Modifying rows directly through public fields bypasses them.
 #[derive(Clone)]
 pub struct VirtualData {
     pub metas: Vec<Meta>,
     pub columns: Vec<Column>,
     pub rows: Vec<Row>,
+    pub(crate) constraints: Vec<KeyConstraint>,
+    pub(crate) row_validators: Vec<RowValidator>,
+    pub(crate) foreign_keys: Vec<ForeignKey>,
+    pub(crate) derived_columns: Vec<DerivedColumn>,
+    pub(crate) changes: ChangeTracker,
 }
 
 impl Default for VirtualData {
@@ -38,9 +55,48 @@ impl VCont for VirtualData {
             metas: vec![],
             columns: vec![],
             rows: vec![],
+            constraints: vec![],
+            row_validators: vec![],
+            foreign_keys: vec![],
+            derived_columns: vec![],
+            changes: ChangeTracker::default(),
         }
     }
 
+    /// Sort rows by sort keys
+    ///
+    /// Sort only changes an order of rows, thus constraints are not affected.
+    fn sort_rows(&mut self, keys: &[SortKey]) -> DcsvResult<()> {
+        let names = keys
+            .iter()
+            .map(|k| {
+                self.columns
+                    .get(k.column)
+                    .map(|c| c.name.as_str())
+                    .ok_or(DcsvError::OutOfRangeError)
+            })
+            .collect::<DcsvResult<Vec<_>>>()?;
+        let values = self
+            .rows
+            .iter()
+            .map(|row| {
+                names
+                    .iter()
+                    .map(|name| {
+                        row.get_cell_value(name).ok_or_else(|| {
+                            DcsvError::InvalidRowData(
+                                "Failed to get row data while sorting".to_string(),
+                            )
+                        })
+                    })
+                    .collect::<DcsvResult<Vec<_>>>()
+            })
+            .collect::<DcsvResult<Vec<_>>>()?;
+        let indices = sorted_indices(keys, &values);
+        self.permute_rows(&indices);
+        Ok(())
+    }
+
     /// Move given row to a target row index
     fn move_row(&mut self, src_index: usize, target_index: usize) -> DcsvResult<()> {
         let row_count = self.get_row_count();
@@ -81,6 +137,7 @@ impl VCont for VirtualData {
             }
             Ordering::Equal => (),
         }
+        self.changes.move_row(src_index, target_index);
         Ok(())
     }
 
@@ -126,6 +183,7 @@ impl VCont for VirtualData {
             }
             Ordering::Equal => (),
         }
+        self.changes.move_column(src_index, target_index);
         Ok(())
     }
 
@@ -149,27 +207,76 @@ impl VCont for VirtualData {
             )));
         }
 
-        let previous = self.columns[column_index].rename(new_name);
-        for row in &mut self.rows {
-            row.rename_column(&previous, new_name);
-        }
+        self.rename_column_unchecked(column_index, new_name);
         Ok(())
     }
 
     /// Set values to a column
     ///
     /// Given value will override every row's value
+    /// This method will fail when given value breaks column's key constraint, row validators or
+    /// enforced foreign keys.
     fn set_column(&mut self, column_index: usize, value: Value) -> DcsvResult<()> {
         if !self.is_valid_cell_coordinate(0, column_index) {
             return Err(DcsvError::OutOfRangeError);
         }
 
         let column = &self.columns[column_index].name;
-        let col_meta = &mut self.metas[column_index];
+        self.check_not_derived(column)?;
 
-        for row in &mut self.rows {
-            col_meta.update_width_from_value(&value);
-            row.update_cell_value(column, value.clone());
+        // Derived columns are recomputed per row, thus replace rows with candidates
+        if self.derived_columns.iter().any(|d| d.has_column(column)) {
+            self.is_valid_column_data(column_index, &value)?;
+            let candidates = self
+                .rows
+                .iter()
+                .enumerate()
+                .map(|(index, row)| {
+                    let mut candidate = row.clone();
+                    candidate.update_cell_value(column, value.clone());
+                    (index, candidate, vec![column.clone()])
+                })
+                .collect::<Vec<_>>();
+            return self.replace_rows(candidates);
+        }
+
+        // Every row gets a same value, thus validate whole rows with new indices. Indices are
+        // committed only after every check passes.
+        let mut constraints = None;
+        if self.constraints.iter().any(|c| c.has_column(column)) {
+            let mut rows = self.rows.clone();
+            for row in &mut rows {
+                row.update_cell_value(column, value.clone());
+            }
+            let mut rebuilt = self.constraints.clone();
+            for constraint in &mut rebuilt {
+                constraint.build(&rows)?;
+            }
+            constraints.replace(rebuilt);
+        }
+
+        if self.row_validators.iter().any(|v| v.has_column(column)) {
+            for (index, row) in self.rows.iter().enumerate() {
+                let mut candidate = row.clone();
+                candidate.update_cell_value(column, value.clone());
+                self.check_row_validators(index, &candidate, Some(&[column.as_str()]))?;
+            }
+        }
+
+        if self.foreign_keys.iter().any(|f| f.has_column(column)) {
+            for row in &self.rows {
+                let mut candidate = row.clone();
+                candidate.update_cell_value(column, value.clone());
+                self.check_foreign_keys(&candidate, Some(&[column.as_str()]))?;
+            }
+        }
+
+        if let Some(constraints) = constraints {
+            self.constraints = constraints;
+        }
+        self.metas[column_index].update_width_from_value(&value);
+        for row in 0..self.rows.len() {
+            self.update_cell(row, column_index, value.clone());
         }
         Ok(())
     }
@@ -201,15 +308,38 @@ impl VCont for VirtualData {
             }
         }
 
-        // It is safe to unwrap because row_number
-        // was validated by is_valid_cell_coordinate method.
-        let row = self.rows.get_mut(row_index).unwrap();
-        for ((idx, col), value) in col_value_iter {
+        // Only clone a row when data has checks over rows
+        if !self.has_row_checks() {
+            for (idx, value) in values.iter().enumerate() {
+                if let Some(value) = value {
+                    self.metas[idx].update_width_from_value(value);
+                    self.update_cell(row_index, idx, value.clone());
+                }
+            }
+            return Ok(());
+        }
+
+        let mut candidate = self.rows[row_index].clone();
+        let mut changed = vec![];
+        for ((_, col), value) in col_value_iter.clone() {
+            if let Some(value) = value {
+                candidate.update_cell_value(&col.name, value.clone());
+                changed.push(col.name.as_str());
+            }
+        }
+        self.derive_row(&mut candidate)?;
+        changed.extend(self.derived_columns.iter().map(|d| d.column.as_str()));
+        self.check_key_constraints(Some(row_index), &candidate)?;
+        self.check_row_validators(row_index, &candidate, Some(&changed))?;
+        self.check_foreign_keys(&candidate, Some(&changed))?;
+
+        for ((idx, _), value) in col_value_iter {
             if let Some(value) = value {
                 self.metas[idx].update_width_from_value(value);
-                row.update_cell_value(&col.name, value.clone())
             }
         }
+        self.replace_row(row_index, candidate);
+        self.update_derived_width(row_index);
 
         Ok(())
     }
@@ -243,13 +373,29 @@ impl VCont for VirtualData {
             }
         }
 
-        // It is safe to unwrap because row_number
-        // was validated by is_valid_cell_coordinate method.
-        let row = self.rows.get_mut(row_index).unwrap();
-        for ((idx, col), value) in col_value_iter {
+        // Only clone a row when data has checks over rows
+        if !self.has_row_checks() {
+            for (idx, value) in values.iter().enumerate() {
+                self.metas[idx].update_width_from_value(value);
+                self.update_cell(row_index, idx, value.clone());
+            }
+            return Ok(());
+        }
+
+        let mut candidate = self.rows[row_index].clone();
+        for ((_, col), value) in col_value_iter.clone() {
+            candidate.update_cell_value(&col.name, value.clone());
+        }
+        self.derive_row(&mut candidate)?;
+        self.check_key_constraints(Some(row_index), &candidate)?;
+        self.check_row_validators(row_index, &candidate, None)?;
+        self.check_foreign_keys(&candidate, None)?;
+
+        for ((idx, _), value) in col_value_iter {
             self.metas[idx].update_width_from_value(value);
-            row.update_cell_value(&col.name, value.clone());
         }
+        self.replace_row(row_index, candidate);
+        self.update_derived_width(row_index);
 
         Ok(())
     }
@@ -268,8 +414,31 @@ impl VCont for VirtualData {
         let name = self.get_column_if_valid(x, y)?.name.to_owned();
 
         self.is_valid_column_data(y, &value)?;
+        self.check_not_derived(&name)?;
+
+        // Only clone a row when the column is a part of a key, a row validator or a derived
+        // column
+        if self.constraints.iter().any(|c| c.has_column(&name))
+            || self.row_validators.iter().any(|v| v.has_column(&name))
+            || self.foreign_keys.iter().any(|f| f.has_column(&name))
+            || self.derived_columns.iter().any(|d| d.has_column(&name))
+        {
+            let mut candidate = self.rows[x].clone();
+            candidate.update_cell_value(&name, value.clone());
+            self.derive_row(&mut candidate)?;
+            let mut changed = vec![name.as_str()];
+            changed.extend(self.derived_columns.iter().map(|d| d.column.as_str()));
+            self.check_key_constraints(Some(x), &candidate)?;
+            self.check_row_validators(x, &candidate, Some(&changed))?;
+            self.check_foreign_keys(&candidate, Some(&changed))?;
+            self.metas[y].update_width_from_value(&value);
+            self.replace_row(x, candidate);
+            self.update_derived_width(x);
+            return Ok(());
+        }
+
         self.metas[y].update_width_from_value(&value);
-        self.rows[x].update_cell_value(&name, value);
+        self.update_cell(x, y, value);
 
         Ok(())
     }
@@ -305,6 +474,13 @@ impl VCont for VirtualData {
                 new_row.insert_cell(&col.name, col.get_default_value());
             }
         }
+        self.derive_row(&mut new_row)?;
+        self.check_key_constraints(None, &new_row)?;
+        self.check_row_validators(row_index, &new_row, None)?;
+        self.check_foreign_keys(&new_row, None)?;
+        for constraint in &mut self.constraints {
+            constraint.insert(&new_row);
+        }
         for (col, value) in self
             .metas
             .iter_mut()
@@ -313,6 +489,7 @@ impl VCont for VirtualData {
             col.update_width_from_value(value)
         }
         self.rows.insert(row_index, new_row);
+        self.changes.insert_row(row_index);
         Ok(())
     }
 
@@ -340,6 +517,7 @@ impl VCont for VirtualData {
         meta.set_width(max_width);
         self.metas.insert(column_index, meta);
         self.columns.insert(column_index, new_column);
+        self.changes.insert_column(column_index);
         Ok(())
     }
 
@@ -352,6 +530,10 @@ impl VCont for VirtualData {
             return false;
         }
         let removed = self.rows.remove(row_index);
+        self.changes.delete_row(row_index);
+        for constraint in &mut self.constraints {
+            constraint.remove(&removed);
+        }
         let to_be_updated_colum_index = removed
             .get_iterator(&self.columns)
             .enumerate()
@@ -378,22 +560,10 @@ impl VCont for VirtualData {
     }
 
     /// Delete a column with given column index
+    ///
+    /// Key constraints, row validators and foreign keys which include the column are also removed.
     fn delete_column(&mut self, column_index: usize) -> DcsvResult<()> {
-        let name = self.get_column_if_valid(0, column_index)?.name.to_owned();
-
-        for row in &mut self.rows {
-            row.remove_cell(&name);
-        }
-
-        self.metas.remove(column_index);
-        self.columns.remove(column_index);
-
-        // If column is empty, drop all rows
-        if self.get_column_count() == 0 {
-            self.rows = vec![];
-        }
-
-        Ok(())
+        self.take_column(column_index).map(|_| ())
     }
 
     /// Get total rows count
@@ -410,13 +580,45 @@ impl VCont for VirtualData {
     fn drop_data(&mut self) {
         self.columns.clear();
         self.rows.clear();
+        self.constraints.clear();
+        self.row_validators.clear();
+        self.foreign_keys.clear();
+        self.derived_columns.clear();
+        self.changes.clear();
     }
 
     /// Apply closure to all values
+    ///
+    /// This bypasses row validators and foreign keys. Values are rolled back when applied values
+    /// violate key constraints, e.g. "01" and "1" both become a same number.
     fn apply_all<F: FnMut(&mut Value)>(&mut self, mut f: F) {
-        for row in &mut self.rows {
-            for value in row.values.values_mut() {
-                f(value)
+        // Previous values of changed cells for rollback and change tracking
+        let mut edits = vec![];
+        for (row_index, row) in self.rows.iter_mut().enumerate() {
+            for (column_index, column) in self.columns.iter().enumerate() {
+                if let Some(value) = row.values.get_mut(&column.name) {
+                    let previous = value.clone();
+                    f(value);
+                    if *value != previous {
+                        edits.push((row_index, column_index, previous));
+                    }
+                }
+            }
+        }
+        if self
+            .constraints
+            .iter_mut()
+            .any(|c| c.build(&self.rows).is_err())
+        {
+            for (row, column, previous) in edits {
+                self.rows[row].update_cell_value(&self.columns[column].name, previous);
+            }
+            self.rebuild_key_indices();
+            return;
+        }
+        for (row, column, previous) in &edits {
+            if let Some(value) = self.rows[*row].get_cell_value(&self.columns[*column].name) {
+                self.changes.set_cell(*row, *column, previous, value);
             }
         }
     }
@@ -532,7 +734,7 @@ impl VirtualData {
     }
 
     /// Get read only data from virtual data, but as reference
-    pub fn read_only_ref(&self) -> ReadOnlyDataRef {
+    pub fn read_only_ref(&self) -> ReadOnlyDataRef<'_> {
         ReadOnlyDataRef::from(self)
     }
 
@@ -598,6 +800,7 @@ impl VirtualData {
         let max_width = UnicodeWidthStr::width(column_name).max(default_value.get_width());
         meta.set_width(max_width);
         self.metas.insert(column_index, meta);
+        self.changes.insert_column(column_index);
         Ok(())
     }
 
@@ -608,58 +811,222 @@ impl VirtualData {
     /// * column  : column's index
     /// * limiter : Target limiter
     /// * panic   : If true, failed set will occur panic
+    ///
+    /// When panic is false, unqualified values are replaced with limiter's default value. Use
+    /// set_limiter_with_mode to get a report of unqualified values.
     pub fn set_limiter(
         &mut self,
         column: usize,
         limiter: &ValueLimiter,
         panic: bool,
     ) -> DcsvResult<()> {
-        let column = &mut self.columns[column];
-        for (index, row) in self.rows.iter_mut().enumerate() {
-            let mut qualified = true;
-            let mut converted = None;
-            let mut convert_to = None;
-            if let Some(value) = row.get_cell_value(&column.name) {
-                // Check if value can be converted at most
-                if let Some(ttype) = limiter.is_convertible(value) {
-                    converted.replace(Value::from_str(&value.to_string(), ttype)?);
-                    convert_to = Some(ttype);
-                }
+        let mode = if panic {
+            LimiterMode::Strict
+        } else {
+            LimiterMode::Overwrite
+        };
+        self.set_limiter_with_mode(column, limiter, mode)?;
+        Ok(())
+    }
 
-                // Check if value qualify limiter condition
-                if !limiter.qualify(converted.as_ref().unwrap_or(value)) {
-                    qualified = false;
-                    convert_to = None;
-                    if panic {
+    /// Set a limiter to a column with given mode
+    ///
+    /// Values which can be converted to limiter's type are converted. Unqualified values are
+    /// treated by mode and returned as a report.
+    ///
+    /// - Strict    : Fail on a first unqualified value without modifying data
+    /// - Overwrite : Replace unqualified values with limiter's default value
+    /// - Collect   : Keep unqualified values untouched
+    ///
+    /// # Args
+    ///
+    /// * column  : column's index
+    /// * limiter : Target limiter
+    /// * mode    : How to treat unqualified values
+    pub fn set_limiter_with_mode(
+        &mut self,
+        column: usize,
+        limiter: &ValueLimiter,
+        mode: LimiterMode,
+    ) -> DcsvResult<ValidationReport> {
+        let (updates, report) = self.conform_column(column, limiter, mode)?;
+        let name = self.columns[column].name.clone();
+
+        // Type conversion can make duplicate keys
+        let mut conversions = vec![(name, updates)];
+        self.check_converted_keys(&conversions, &[], &[])?;
+        // It is safe to unwrap because conversions has a single element
+        let (_, updates) = conversions.pop().unwrap();
+        self.apply_conformed(column, limiter, updates);
+        Ok(report)
+    }
+
+    /// Conform values of a column to a limiter without modifying data
+    ///
+    /// This returns converted values as pairs of row index and value with a report of unqualified
+    /// values. Strict mode fails on a first unqualified value.
+    pub(crate) fn conform_column(
+        &self,
+        column: usize,
+        limiter: &ValueLimiter,
+        mode: LimiterMode,
+    ) -> DcsvResult<(Vec<(usize, Value)>, ValidationReport)> {
+        self.conform_column_with_missing(column, limiter, mode, &[])
+    }
+
+    /// Conform values of a column where missing value tokens are treated as empty values
+    ///
+    /// Missing values are replaced with empty values even when collect mode keeps them
+    /// unqualified.
+    pub(crate) fn conform_column_with_missing(
+        &self,
+        column: usize,
+        limiter: &ValueLimiter,
+        mode: LimiterMode,
+        missing: &[&String],
+    ) -> DcsvResult<(Vec<(usize, Value)>, ValidationReport)> {
+        let name = &self
+            .columns
+            .get(column)
+            .ok_or(DcsvError::OutOfRangeError)?
+            .name;
+        let mut report = ValidationReport::new();
+        let mut updates = vec![];
+        for (index, row) in self.rows.iter().enumerate() {
+            let value = row.get_cell_value(name).ok_or_else(|| {
+                DcsvError::InvalidRowData(
+                    "Failed to get row data while setting limiter".to_string(),
+                )
+            })?;
+            let empty = Value::default();
+            let is_missing =
+                !missing.is_empty() && missing.iter().any(|t| **t == value.to_string());
+            let value = if is_missing { &empty } else { value };
+
+            match limiter.conform(value) {
+                Ok(converted) => updates.push((index, converted)),
+                Err(rule) => {
+                    if mode == LimiterMode::Strict {
                         return Err(DcsvError::InvalidCellData(format!(
-                            "Cell {},{} doesn't match limiter's qualification",
-                            index, column.name
+                            "Cell {},{} doesn't match limiter's qualification : {}",
+                            index, name, rule
                         )));
                     }
+                    report.push(Violation {
+                        row: index,
+                        columns: vec![name.clone()],
+                        values: vec![value.clone()],
+                        rule,
+                    });
+                    if mode == LimiterMode::Overwrite {
+                        // Limiter might only have a single "type" value
+                        updates.push((index, limiter.get_default_or_empty()));
+                    } else if is_missing {
+                        updates.push((index, empty));
+                    }
                 }
-            } else {
-                return Err(DcsvError::InvalidRowData(
-                    "Failed to get row data while setting limiter".to_string(),
-                ));
             }
+        }
+        Ok((updates, report))
+    }
 
-            if let Some(ttype) = convert_to {
-                row.change_cell_type(&column.name, ttype)?;
-            } else if !qualified && !panic {
-                // Force update to defualt value
-                // It is mostly safe to unwrap because default is required for pattern or variant
-                // but, limiter might only have a single "type" value
-                row.update_cell_value(
-                    &column.name,
-                    limiter
-                        .get_default()
-                        .unwrap_or(&Value::empty(limiter.get_type()))
-                        .clone(),
-                );
+    /// Apply conformed values and a limiter to a column
+    pub(crate) fn apply_conformed(
+        &mut self,
+        column: usize,
+        limiter: &ValueLimiter,
+        updates: Vec<(usize, Value)>,
+    ) {
+        for (index, value) in updates {
+            self.metas[column].update_width_from_value(&value);
+            self.update_cell(index, column, value);
+        }
+        self.columns[column].set_limiter(limiter.clone());
+        // Type conversion can change keys
+        self.rebuild_key_indices();
+    }
+
+    /// Validate whole data and return every violation
+    ///
+    /// This checks column limiters, key constraints, row validators and foreign keys. Foreign
+    /// keys are checked against snapshots of referenced keys. Violations are ordered by rows.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::new();
+        let empty = Value::default();
+        for (index, row) in self.rows.iter().enumerate() {
+            for col in &self.columns {
+                let value = row.get_cell_value(&col.name).unwrap_or(&empty);
+                if let Err(rule) = col.limiter.check(value) {
+                    report.push(Violation {
+                        row: index,
+                        columns: vec![col.name.clone()],
+                        values: vec![value.clone()],
+                        rule,
+                    });
+                }
+            }
+
+            for validator in &self.row_validators {
+                if let Err(message) = validate_row(validator, row) {
+                    report.push(Violation {
+                        row: index,
+                        columns: validator.get_columns().to_vec(),
+                        values: validator
+                            .get_columns()
+                            .iter()
+                            .map(|c| row.get_cell_value(c).unwrap_or(&empty).clone())
+                            .collect(),
+                        rule: ViolationRule::RowValidator {
+                            name: validator.get_name().to_string(),
+                            message,
+                        },
+                    });
+                }
+            }
+
+            for foreign_key in &self.foreign_keys {
+                if let Some(key) = foreign_key.dangling_key(row) {
+                    report.push(Violation {
+                        row: index,
+                        columns: foreign_key.get_columns().to_vec(),
+                        values: key,
+                        rule: ViolationRule::ForeignKey(foreign_key.get_name().to_string()),
+                    });
+                }
             }
         }
-        column.set_limiter(limiter.clone());
-        Ok(())
+
+        for constraint in &self.constraints {
+            report
+                .violations
+                .extend(self.get_key_violations(constraint));
+        }
+        report.violations.sort_by_key(|v| v.row);
+        report
+    }
+
+    /// Find rows which violate a key constraint
+    ///
+    /// This doesn't rely on constraint's index. A first row of a duplicate key is not a
+    /// violation.
+    pub(crate) fn get_key_violations(&self, constraint: &KeyConstraint) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut keys = HashSet::new();
+        for (index, row) in self.rows.iter().enumerate() {
+            if let Some(key) = constraint.key_of(row) {
+                let empty_key = constraint.get_kind() == KeyKind::PrimaryKey
+                    && key.iter().any(|v| v.is_empty());
+                if empty_key || !keys.insert(key.clone()) {
+                    violations.push(Violation {
+                        row: index,
+                        columns: constraint.get_columns().to_vec(),
+                        values: key,
+                        rule: ViolationRule::Key(constraint.get_kind()),
+                    });
+                }
+            }
+        }
+        violations
     }
 
     /// Qualify data and get reference of qualifed rows.
@@ -710,47 +1077,359 @@ impl VirtualData {
         Ok(rows)
     }
 
-    /// Export virtual data's schema(limiter) as string form
-    ///
-    /// Schema is expressed as csv value. Each line is structured with following order.
-    ///
-    /// - column
-    /// - type
-    /// - default
-    /// - variant
-    /// - pattern
-    pub fn export_schema(&self) -> String {
-        let mut schema = format!("{}\n", SCHEMA_HEADER);
-        for col in &self.columns {
-            let mut line = col.name.clone() + ",";
-            let limiter = &col.limiter;
-            line.push_str(&limiter.get_type().to_string());
-            line.push(',');
-            line.push_str(
-                &limiter
-                    .get_default()
-                    .map(|s| s.to_string())
-                    .unwrap_or_default(),
-            );
-            line.push(',');
-            line.push_str(
-                &limiter
-                    .get_variant()
-                    .map(|s| s.iter().map(|s| s.to_string()).collect::<Vec<String>>())
-                    .unwrap_or_default()
-                    .join(" "),
-            );
-            line.push(',');
-            line.push_str(
-                &limiter
-                    .get_pattern()
-                    .map(|s| s.to_string())
-                    .unwrap_or_default(),
-            );
+    /// Get indices of rows which match a query
+    pub fn query_rows(&self, query: &Query) -> DcsvResult<Vec<usize>> {
+        for column in query.get_columns() {
+            if !self.columns.iter().any(|c| c.name == column) {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "Query refers to a non existent column \"{}\"",
+                    column
+                )));
+            }
+        }
+        Ok(self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| query.matches(|column| row.get_cell_value(column)))
+            .map(|(index, _)| index)
+            .collect())
+    }
+
+    /// Get a new virtual data with rows which match a query
+    ///
+    /// Limiters, constraints, row validators and foreign keys are kept.
+    pub fn filter(&self, query: &Query) -> DcsvResult<Self> {
+        let rows = self
+            .query_rows(query)?
+            .into_iter()
+            .map(|index| self.rows[index].clone())
+            .collect();
+        let mut data = Self {
+            metas: self
+                .columns
+                .iter()
+                .map(|c| {
+                    let mut meta = Meta::new();
+                    meta.set_width(UnicodeWidthStr::width(c.name.as_str()));
+                    meta
+                })
+                .collect(),
+            columns: self.columns.clone(),
+            rows,
+            constraints: self.constraints.clone(),
+            row_validators: self.row_validators.clone(),
+            foreign_keys: self.foreign_keys.clone(),
+            derived_columns: self.derived_columns.clone(),
+            changes: ChangeTracker::default(),
+        };
+        data.rebuild_key_indices();
+        data.update_width_global();
+        data.mark_clean();
+        Ok(data)
+    }
+
+    /// Get a new virtual data with rows and columns swapped
+    ///
+    /// Header becomes a first column. When header is true, first column becomes a new header and
+    /// other columns become rows. Otherwise every column becomes a row with arbitrary header
+    /// names. Transposing twice with header returns an identical data.
+    ///
+    /// A column of transposed data mixes values of different columns, thus values are converted
+    /// into texts and every column is a text column without a limiter. Constraints, row
+    /// validators, foreign keys and derived columns are not kept. This fails when first column
+    /// has duplicate or empty values for a header.
+    pub fn transpose(&self, header: bool) -> DcsvResult<Self> {
+        let skip = usize::from(header && !self.columns.is_empty());
+        let names = if skip == 1 {
+            std::iter::once(Ok(self.columns[0].name.clone()))
+                .chain((0..self.get_row_count()).map(|row| {
+                    self.get_cell(row, 0)
+                        .map(|v| v.to_string())
+                        .ok_or(DcsvError::OutOfRangeError)
+                }))
+                .collect::<DcsvResult<Vec<_>>>()?
+        } else {
+            make_arbitrary_column(self.get_row_count() + 1)
+        };
+
+        let mut seen = HashSet::new();
+        for name in &names {
+            if name.is_empty() || !seen.insert(name) {
+                return Err(DcsvError::InvalidColumn(format!(
+                    "\"{}\" cannot be a header of transposed data",
+                    name
+                )));
+            }
+        }
+
+        let rows = self
+            .columns
+            .iter()
+            .skip(skip)
+            .map(|column| {
+                let mut row = Row::new();
+                row.insert_cell(&names[0], Value::Text(column.name.clone()));
+                for (name, source) in names.iter().skip(1).zip(&self.rows) {
+                    let value = source
+                        .get_cell_value(&column.name)
+                        .map(|v| v.to_string())
+                        .unwrap_or_default();
+                    row.insert_cell(name, Value::Text(value));
+                }
+                row
+            })
+            .collect();
+        let mut data = Self {
+            metas: names
+                .iter()
+                .map(|name| {
+                    let mut meta = Meta::new();
+                    meta.set_width(UnicodeWidthStr::width(name.as_str()));
+                    meta
+                })
+                .collect(),
+            columns: names.iter().map(|name| Column::empty(name)).collect(),
+            rows,
+            constraints: vec![],
+            row_validators: vec![],
+            foreign_keys: vec![],
+            derived_columns: vec![],
+            changes: ChangeTracker::default(),
+        };
+        data.update_width_global();
+        data.mark_clean();
+        Ok(data)
+    }
+
+    /// Add a unique constraint over given columns
+    ///
+    /// Columns can be either column names or column indices. Multiple columns compose a single
+    /// key. This fails when existing rows already violate the constraint.
+    pub fn add_unique_constraint(&mut self, columns: &[impl AsRef<str>]) -> DcsvResult<()> {
+        let columns = self.get_column_names(columns)?;
+        if self.constraints.iter().any(|c| c.get_columns() == columns) {
+            return Err(DcsvError::InvalidColumn(format!(
+                "Key constraint for ({}) already exists",
+                columns.join(",")
+            )));
+        }
+        let mut constraint = KeyConstraint::new(KeyKind::Unique, columns);
+        constraint.build(&self.rows)?;
+        self.constraints.push(constraint);
+        Ok(())
+    }
+
+    /// Set a primary key over given columns
+    ///
+    /// Columns can be either column names or column indices. Multiple columns compose a composite
+    /// key. Previous primary key is replaced. This fails when existing rows already violate the
+    /// constraint.
+    pub fn set_primary_key(&mut self, columns: &[impl AsRef<str>]) -> DcsvResult<()> {
+        let columns = self.get_column_names(columns)?;
+        let mut constraint = KeyConstraint::new(KeyKind::PrimaryKey, columns);
+        constraint.build(&self.rows)?;
+        self.constraints
+            .retain(|c| c.get_kind() != KeyKind::PrimaryKey);
+        self.constraints.insert(0, constraint);
+        Ok(())
+    }
+
+    /// Get a primary key constraint
+    pub fn get_primary_key(&self) -> Option<&KeyConstraint> {
+        self.constraints
+            .iter()
+            .find(|c| c.get_kind() == KeyKind::PrimaryKey)
+    }
+
+    /// Get all key constraints
+    pub fn get_key_constraints(&self) -> &[KeyConstraint] {
+        &self.constraints
+    }
+
+    /// Remove a key constraint with given columns
+    ///
+    /// This returns false when no constraint matches given columns.
+    pub fn remove_key_constraint(&mut self, columns: &[impl AsRef<str>]) -> DcsvResult<bool> {
+        let columns = self.get_column_names(columns)?;
+        let count = self.constraints.len();
+        self.constraints.retain(|c| c.get_columns() != columns);
+        Ok(count != self.constraints.len())
+    }
+
+    /// Add a row validator over given columns
+    ///
+    /// Columns can be either column names or column indices. Validator function receives values
+    /// of given columns in given order. Row validator is checked whenever a row is edited but
+    /// existing rows are not validated. Use validate_rows to check existing rows.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use dcsv::{Reader, Value};
+    ///
+    /// let mut data = Reader::new()
+    ///     .data_from_stream("start,end\n1,3".as_bytes())
+    ///     .expect("Failed to read data");
+    /// data.add_row_validator("date_order", &["start", "end"], |values| {
+    ///     if values[0] <= values[1] {
+    ///         Ok(())
+    ///     } else {
+    ///         Err("end is earlier than start".to_string())
+    ///     }
+    /// })
+    /// .expect("Failed to add row validator");
+    /// ```
+    pub fn add_row_validator<F>(
+        &mut self,
+        name: &str,
+        columns: &[impl AsRef<str>],
+        function: F,
+    ) -> DcsvResult<()>
+    where
+        F: Fn(&[&Value]) -> Result<(), String> + Send + Sync + 'static,
+    {
+        if self.row_validators.iter().any(|v| v.get_name() == name) {
+            return Err(DcsvError::InvalidColumn(format!(
+                "Row validator \"{}\" already exists",
+                name
+            )));
+        }
+        let columns = self.get_column_names(columns)?;
+        self.row_validators
+            .push(RowValidator::new(name, &columns, function));
+        Ok(())
+    }
 
-            schema.push_str(&(line + "\n"));
+    /// Remove a row validator with given name
+    ///
+    /// This returns false when no validator has the name.
+    pub fn remove_row_validator(&mut self, name: &str) -> bool {
+        let count = self.row_validators.len();
+        self.row_validators.retain(|v| v.get_name() != name);
+        count != self.row_validators.len()
+    }
+
+    /// Get all row validators
+    pub fn get_row_validators(&self) -> &[RowValidator] {
+        &self.row_validators
+    }
+
+    /// Validate every row with row validators
+    ///
+    /// This returns all violations instead of failing fast.
+    pub fn validate_rows(&self) -> Vec<RowViolation> {
+        let mut violations = vec![];
+        for (index, row) in self.rows.iter().enumerate() {
+            for validator in &self.row_validators {
+                if let Err(message) = validate_row(validator, row) {
+                    violations.push(RowViolation {
+                        row: index,
+                        validator: validator.get_name().to_string(),
+                        columns: validator.get_columns().to_vec(),
+                        message,
+                    });
+                }
+            }
         }
-        schema
+        violations
+    }
+
+    /// Add a foreign key which refers to columns of another virtual data
+    ///
+    /// Columns can be either column names or column indices. Referenced keys are captured from a
+    /// referenced data as a snapshot, use sync_foreign_key to update the snapshot after editing
+    /// referenced data. Keys are compared by text form. When enforce is true, edits which make a
+    /// dangling reference are refused.
+    ///
+    /// # Args
+    ///
+    /// * name       : Name of a foreign key
+    /// * columns    : Referencing columns of this data
+    /// * referenced : Referenced data
+    /// * referenced_columns : Referenced columns of referenced data
+    /// * enforce    : Whether to refuse edits which break references
+    pub fn add_foreign_key(
+        &mut self,
+        name: &str,
+        columns: &[impl AsRef<str>],
+        referenced: &VirtualData,
+        referenced_columns: &[impl AsRef<str>],
+        enforce: bool,
+    ) -> DcsvResult<()> {
+        if self.foreign_keys.iter().any(|f| f.get_name() == name) {
+            return Err(DcsvError::InvalidColumn(format!(
+                "Foreign key \"{}\" already exists",
+                name
+            )));
+        }
+        let columns = self.get_column_names(columns)?;
+        let referenced_columns = referenced.get_column_names(referenced_columns)?;
+        if columns.len() != referenced_columns.len() {
+            return Err(DcsvError::InvalidColumn(format!(
+                "Foreign key has {} columns while referenced key has {} columns",
+                columns.len(),
+                referenced_columns.len()
+            )));
+        }
+        let mut foreign_key = ForeignKey::new(name, columns, referenced_columns, enforce);
+        foreign_key.sync(referenced.get_key_set(foreign_key.get_referenced_columns()));
+        self.foreign_keys.push(foreign_key);
+        Ok(())
+    }
+
+    /// Update snapshot of referenced keys with a referenced data
+    pub fn sync_foreign_key(&mut self, name: &str, referenced: &VirtualData) -> DcsvResult<()> {
+        let foreign_key = self.get_foreign_key_mut(name)?;
+        let keys = referenced.get_key_set(foreign_key.get_referenced_columns());
+        foreign_key.sync(keys);
+        Ok(())
+    }
+
+    /// Remove a foreign key with given name
+    ///
+    /// This returns false when no foreign key has the name.
+    pub fn remove_foreign_key(&mut self, name: &str) -> bool {
+        let count = self.foreign_keys.len();
+        self.foreign_keys.retain(|f| f.get_name() != name);
+        count != self.foreign_keys.len()
+    }
+
+    /// Get all foreign keys
+    pub fn get_foreign_keys(&self) -> &[ForeignKey] {
+        &self.foreign_keys
+    }
+
+    /// Find dangling references of a foreign key against a referenced data
+    ///
+    /// This checks against a given referenced data, not a snapshot.
+    pub fn check_foreign_key(
+        &self,
+        name: &str,
+        referenced: &VirtualData,
+    ) -> DcsvResult<Vec<DanglingReference>> {
+        let foreign_key = self
+            .foreign_keys
+            .iter()
+            .find(|f| f.get_name() == name)
+            .ok_or_else(|| {
+                DcsvError::InvalidColumn(format!("Foreign key \"{}\" doesn't exist", name))
+            })?;
+        let keys = referenced.get_key_set(foreign_key.get_referenced_columns());
+        let mut dangling = vec![];
+        for (index, row) in self.rows.iter().enumerate() {
+            if let Some(key) = foreign_key.key_of(row) {
+                if !keys.contains(&text_key(&key)) {
+                    dangling.push(DanglingReference {
+                        row: index,
+                        foreign_key: name.to_string(),
+                        columns: foreign_key.get_columns().to_vec(),
+                        key,
+                    });
+                }
+            }
+        }
+        Ok(dangling)
     }
 
     // <DRY>
@@ -799,13 +1478,12 @@ impl VirtualData {
     /// Check if given value corresponds to column limiter
     fn is_valid_column_data(&self, column: usize, value: &Value) -> DcsvResult<()> {
         if let Some(col) = self.columns.get(column) {
-            if col.limiter.qualify(value) {
-                Ok(())
-            } else {
-                Err(DcsvError::InvalidCellData(
-                    "Given cell data failed to match limiter's restriction".to_string(),
+            col.limiter.check(value).map_err(|rule| {
+                DcsvError::InvalidCellData(format!(
+                    "\"{}\" violates {} of column \"{}\"",
+                    value, rule, col.name
                 ))
-            }
+            })
         } else {
             Err(DcsvError::InvalidRowData(format!(
                 "Given column index \"{}\" doesn't exist",
@@ -814,6 +1492,445 @@ impl VirtualData {
         }
     }
 
+    /// Convert column names or indices into column names
+    pub(crate) fn get_column_names(&self, columns: &[impl AsRef<str>]) -> DcsvResult<Vec<String>> {
+        if columns.is_empty() {
+            return Err(DcsvError::InvalidColumn(
+                "At least one column is required".to_string(),
+            ));
+        }
+        columns
+            .iter()
+            .map(|c| {
+                self.try_get_column_index(c.as_ref())
+                    .map(|idx| self.columns[idx].name.clone())
+                    .ok_or_else(|| {
+                        DcsvError::InvalidColumn(format!("Column \"{}\" doesn't exist", c.as_ref()))
+                    })
+            })
+            .collect()
+    }
+
+    /// Check if a candidate row violates key constraints
+    ///
+    /// Row index is a row to be replaced by the candidate, if any.
+    fn check_key_constraints(&self, row_index: Option<usize>, candidate: &Row) -> DcsvResult<()> {
+        let previous = row_index.map(|idx| &self.rows[idx]);
+        for constraint in &self.constraints {
+            constraint.check(previous, candidate)?;
+        }
+        Ok(())
+    }
+
+    /// Check if a candidate row violates row validators
+    ///
+    /// When changed columns are given, only validators over changed columns are checked.
+    fn check_row_validators(
+        &self,
+        row_index: usize,
+        candidate: &Row,
+        changed: Option<&[&str]>,
+    ) -> DcsvResult<()> {
+        for validator in &self.row_validators {
+            if let Some(changed) = changed {
+                if !changed.iter().any(|c| validator.has_column(c)) {
+                    continue;
+                }
+            }
+            if let Err(message) = validate_row(validator, candidate) {
+                return Err(DcsvError::ConstraintViolation(
+                    RowViolation {
+                        row: row_index,
+                        validator: validator.get_name().to_string(),
+                        columns: validator.get_columns().to_vec(),
+                        message,
+                    }
+                    .to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if a candidate row makes a dangling reference with enforced foreign keys
+    ///
+    /// When changed columns are given, only foreign keys over changed columns are checked.
+    fn check_foreign_keys(&self, candidate: &Row, changed: Option<&[&str]>) -> DcsvResult<()> {
+        for foreign_key in &self.foreign_keys {
+            if !foreign_key.is_enforced() {
+                continue;
+            }
+            if let Some(changed) = changed {
+                if !changed.iter().any(|c| foreign_key.has_column(c)) {
+                    continue;
+                }
+            }
+            foreign_key.check(candidate)?;
+        }
+        Ok(())
+    }
+
+    /// Get a mutable foreign key by name
+    fn get_foreign_key_mut(&mut self, name: &str) -> DcsvResult<&mut ForeignKey> {
+        self.foreign_keys
+            .iter_mut()
+            .find(|f| f.get_name() == name)
+            .ok_or_else(|| {
+                DcsvError::InvalidColumn(format!("Foreign key \"{}\" doesn't exist", name))
+            })
+    }
+
+    /// Collect a set of keys in text form composed of given columns
+    fn get_key_set(&self, columns: &[String]) -> HashSet<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|c| {
+                        row.get_cell_value(c)
+                            .map(|v| v.to_string())
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Update a value of a cell and record a change
+    ///
+    /// This doesn't check anything, thus coordinate should be valid.
+    pub(crate) fn update_cell(&mut self, row_index: usize, column_index: usize, value: Value) {
+        let name = &self.columns[column_index].name;
+        if let Some(previous) = self.rows[row_index].get_cell_value(name) {
+            self.changes
+                .set_cell(row_index, column_index, previous, &value);
+        }
+        self.rows[row_index].update_cell_value(name, value);
+    }
+
+    /// Replace a row, record changes and update key indices
+    fn replace_row(&mut self, row_index: usize, row: Row) {
+        let previous = std::mem::replace(&mut self.rows[row_index], row);
+        let empty = Value::default();
+        for (index, column) in self.columns.iter().enumerate() {
+            self.changes.set_cell(
+                row_index,
+                index,
+                previous.get_cell_value(&column.name).unwrap_or(&empty),
+                self.rows[row_index]
+                    .get_cell_value(&column.name)
+                    .unwrap_or(&empty),
+            );
+        }
+        for constraint in &mut self.constraints {
+            constraint.remove(&previous);
+            constraint.insert(&self.rows[row_index]);
+        }
+    }
+
+    /// Replace multiple rows at once
+    ///
+    /// Candidates are a row index, a candidate row and changed column names. Derived columns are
+    /// recomputed and every check is done against a final state, thus data is not modified when
+    /// any check fails.
+    pub(crate) fn replace_rows(
+        &mut self,
+        mut candidates: Vec<(usize, Row, Vec<String>)>,
+    ) -> DcsvResult<()> {
+        let derived = self
+            .derived_columns
+            .iter()
+            .map(|d| d.column.clone())
+            .collect::<Vec<_>>();
+        for (index, candidate, changed) in &mut candidates {
+            self.derive_row(candidate)?;
+            changed.extend(derived.iter().cloned());
+            let changed = changed.iter().map(|c| c.as_str()).collect::<Vec<_>>();
+            self.check_row_validators(*index, candidate, Some(&changed))?;
+            self.check_foreign_keys(candidate, Some(&changed))?;
+        }
+
+        self.check_replaced_keys(&candidates)?;
+        self.commit_rows(candidates);
+        Ok(())
+    }
+
+    /// Check rows which are appended after existing rows
+    ///
+    /// Derived columns are recomputed and rows are checked against existing rows and each other,
+    /// thus data is not modified when any check fails.
+    pub(crate) fn check_appended_rows(&self, rows: &mut [Row]) -> DcsvResult<()> {
+        let mut appended = self
+            .constraints
+            .iter()
+            .map(|c| KeyConstraint::new(c.get_kind(), c.get_columns().to_vec()))
+            .collect::<Vec<_>>();
+        for (offset, row) in rows.iter_mut().enumerate() {
+            self.derive_row(row)?;
+            self.check_key_constraints(None, row)?;
+            for keys in &mut appended {
+                keys.check(None, row)?;
+                keys.insert(row);
+            }
+            self.check_row_validators(self.rows.len() + offset, row, None)?;
+            self.check_foreign_keys(row, None)?;
+        }
+        Ok(())
+    }
+
+    /// Append checked rows, record changes and update key indices
+    pub(crate) fn append_rows(&mut self, rows: Vec<Row>) {
+        for row in rows {
+            for constraint in &mut self.constraints {
+                constraint.insert(&row);
+            }
+            for (meta, column) in self.metas.iter_mut().zip(&self.columns) {
+                if let Some(value) = row.get_cell_value(&column.name) {
+                    meta.update_width_from_value(value);
+                }
+            }
+            self.rows.push(row);
+            self.changes.insert_row(self.rows.len() - 1);
+        }
+    }
+
+    /// Check keys of a state where rows are replaced with candidates
+    fn check_replaced_keys(&self, candidates: &[(usize, Row, Vec<String>)]) -> DcsvResult<()> {
+        let positions = candidates
+            .iter()
+            .enumerate()
+            .map(|(position, (index, _, _))| (*index, position))
+            .collect::<HashMap<_, _>>();
+        for constraint in self.constraints.iter().filter(|c| {
+            candidates
+                .iter()
+                .any(|(_, _, changed)| changed.iter().any(|col| c.has_column(col)))
+        }) {
+            let mut keys =
+                KeyConstraint::new(constraint.get_kind(), constraint.get_columns().to_vec());
+            for (index, row) in self.rows.iter().enumerate() {
+                let row = positions
+                    .get(&index)
+                    .map(|p| &candidates[*p].1)
+                    .unwrap_or(row);
+                keys.check(None, row)?;
+                keys.insert(row);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace rows with checked candidates and update widths of changed columns
+    fn commit_rows(&mut self, candidates: Vec<(usize, Row, Vec<String>)>) {
+        for (index, candidate, changed) in candidates {
+            for name in &changed {
+                if let Some(column) = self.columns.iter().position(|c| c.name == *name) {
+                    if let Some(value) = candidate.get_cell_value(name) {
+                        self.metas[column].update_width_from_value(value);
+                    }
+                }
+            }
+            self.replace_row(index, candidate);
+        }
+    }
+
+    /// Write values of cells as they were
+    ///
+    /// This bypasses row validators and foreign keys as apply_all does. Derived columns of a row
+    /// are recomputed unless values of derived columns are given for the row. Data is not
+    /// modified when any cell is out of range or keys are violated.
+    pub(crate) fn restore_cells(&mut self, cells: &[(usize, usize, Value)]) -> DcsvResult<()> {
+        let mut candidates: Vec<(usize, Row, Vec<String>)> = vec![];
+        let mut positions = HashMap::new();
+        for (x, y, value) in cells {
+            let name = self.get_column_if_valid(*x, *y)?.name.clone();
+            let position = *positions.entry(*x).or_insert_with(|| {
+                candidates.push((*x, self.rows[*x].clone(), vec![]));
+                candidates.len() - 1
+            });
+            let (_, row, changed) = &mut candidates[position];
+            row.update_cell_value(&name, value.clone());
+            changed.push(name);
+        }
+        for (_, row, changed) in &mut candidates {
+            if !self.derived_columns.is_empty()
+                && !changed.iter().any(|name| self.is_derived_column(name))
+            {
+                self.derive_row(row)?;
+                changed.extend(self.derived_columns.iter().map(|d| d.column.clone()));
+            }
+        }
+        self.check_replaced_keys(&candidates)?;
+        self.commit_rows(candidates);
+        Ok(())
+    }
+
+    /// Reorder rows with indices of original positions
+    pub(crate) fn permute_rows(&mut self, indices: &[usize]) {
+        self.rows = permute(std::mem::take(&mut self.rows), indices);
+        self.changes.permute_rows(indices);
+    }
+
+    /// Delete a column and get it back with everything attached to it
+    ///
+    /// Key constraints, row validators, foreign keys and derived columns which use the column
+    /// are removed together.
+    pub(crate) fn take_column(&mut self, column_index: usize) -> DcsvResult<RemovedColumn> {
+        let name = self.get_column_if_valid(0, column_index)?.name.to_owned();
+
+        let values = self
+            .rows
+            .iter_mut()
+            .map(|row| row.values.remove(&name).unwrap_or_default())
+            .collect();
+        let constraints = take_items(&mut self.constraints, |c| c.has_column(&name));
+        let row_validators = take_items(&mut self.row_validators, |v| v.has_column(&name));
+        let foreign_keys = take_items(&mut self.foreign_keys, |f| f.has_column(&name));
+        let derived_columns = take_items(&mut self.derived_columns, |d| d.has_column(&name));
+
+        let meta = self.metas.remove(column_index);
+        let column = self.columns.remove(column_index);
+        self.changes.delete_column(column_index);
+
+        // If column is empty, drop all rows
+        if self.get_column_count() == 0 {
+            self.rows = vec![];
+            self.changes.clear_rows();
+        }
+
+        Ok(RemovedColumn {
+            column,
+            meta,
+            values,
+            constraints,
+            row_validators,
+            foreign_keys,
+            derived_columns,
+        })
+    }
+
+    /// Insert a column which was taken by take_column
+    ///
+    /// Rows are created again when a column was the last one.
+    pub(crate) fn restore_column(&mut self, column_index: usize, removed: RemovedColumn) {
+        if self.columns.is_empty() {
+            self.rows = vec![Row::new(); removed.values.len()];
+            for row in 0..self.rows.len() {
+                self.changes.insert_row(row);
+            }
+        }
+        let name = removed.column.name.clone();
+        for (row, value) in self.rows.iter_mut().zip(removed.values) {
+            row.insert_cell(&name, value);
+        }
+        self.metas.insert(column_index, removed.meta);
+        self.columns.insert(column_index, removed.column);
+        self.changes.insert_column(column_index);
+
+        let has_constraints = !removed.constraints.is_empty();
+        restore_items(&mut self.constraints, removed.constraints);
+        restore_items(&mut self.row_validators, removed.row_validators);
+        restore_items(&mut self.foreign_keys, removed.foreign_keys);
+        restore_items(&mut self.derived_columns, removed.derived_columns);
+        if has_constraints {
+            self.rebuild_key_indices();
+        }
+    }
+
+    /// Check if a column can be set directly
+    fn check_not_derived(&self, column: &str) -> DcsvResult<()> {
+        if self.is_derived_column(column) {
+            return Err(DcsvError::InvalidColumn(format!(
+                "Cannot set a derived column \"{}\"",
+                column
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check if data has key constraints, row validators, foreign keys or derived columns
+    fn has_row_checks(&self) -> bool {
+        !self.constraints.is_empty()
+            || !self.row_validators.is_empty()
+            || !self.foreign_keys.is_empty()
+            || !self.derived_columns.is_empty()
+    }
+
+    /// Check if converted values of columns keep keys unique
+    ///
+    /// Conversions are pairs of column name and updates of row index and converted value. Rows
+    /// without an update keep their values. Constraints over removed columns are not checked,
+    /// while added constraints are always checked.
+    pub(crate) fn check_converted_keys(
+        &self,
+        conversions: &[(String, Vec<(usize, Value)>)],
+        removed: &[String],
+        added: &[KeyConstraint],
+    ) -> DcsvResult<()> {
+        let mut converted: HashMap<&str, Vec<Option<&Value>>> = HashMap::new();
+        for (column, updates) in conversions {
+            let values = converted
+                .entry(column.as_str())
+                .or_insert_with(|| vec![None; self.rows.len()]);
+            for (index, value) in updates {
+                values[*index] = Some(value);
+            }
+        }
+        let existing = self.constraints.iter().filter(|c| {
+            conversions.iter().any(|(col, _)| c.has_column(col))
+                && !removed.iter().any(|col| c.has_column(col))
+        });
+        for constraint in existing.chain(added) {
+            let mut keys =
+                KeyConstraint::new(constraint.get_kind(), constraint.get_columns().to_vec());
+            for (row_index, row) in self.rows.iter().enumerate() {
+                // Only key columns are required to check a key
+                let mut candidate = Row::new();
+                for col in constraint.get_columns() {
+                    let value = match converted.get(col.as_str()).and_then(|v| v[row_index]) {
+                        Some(value) => value.clone(),
+                        None => row.get_cell_value(col).cloned().unwrap_or_default(),
+                    };
+                    candidate.insert_cell(col, value);
+                }
+                keys.check(None, &candidate)?;
+                keys.insert(&candidate);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename a column without checking a new name
+    ///
+    /// Rows, constraints, row validators, foreign keys and derived columns follow the new name.
+    pub(crate) fn rename_column_unchecked(&mut self, column_index: usize, new_name: &str) {
+        let previous = self.columns[column_index].rename(new_name);
+        self.changes.rename_column();
+        for row in &mut self.rows {
+            row.rename_column(&previous, new_name);
+        }
+        for constraint in &mut self.constraints {
+            constraint.rename_column(&previous, new_name);
+        }
+        for validator in &mut self.row_validators {
+            validator.rename_column(&previous, new_name);
+        }
+        for foreign_key in &mut self.foreign_keys {
+            foreign_key.rename_column(&previous, new_name);
+        }
+        for derived in &mut self.derived_columns {
+            derived.rename_column(&previous, new_name);
+        }
+    }
+
+    /// Rebuild key indices from rows
+    pub(crate) fn rebuild_key_indices(&mut self) {
+        for constraint in &mut self.constraints {
+            constraint.rebuild(&self.rows);
+        }
+    }
+
     /// Check if given values' length matches column's legnth
     fn check_row_length(&self, values: &[Value]) -> DcsvResult<()> {
         match self.get_column_count().cmp(&values.len()) {
@@ -849,7 +1966,7 @@ impl VirtualData {
         let column = &self
             .columns
             .get(column_index)
-            .ok_or_else(|| DcsvError::OutOfRangeError)?;
+            .ok_or(DcsvError::OutOfRangeError)?;
         let acc = (0..self.get_row_count())
             .filter_map(|idx| self.rows[idx].get_cell_value(&column.name))
             .collect::<Vec<_>>();
@@ -869,6 +1986,53 @@ impl VirtualData {
     }
 }
 
+/// Run a row validator against a row
+fn validate_row(validator: &RowValidator, row: &Row) -> Result<(), String> {
+    let empty = Value::default();
+    let values = validator
+        .get_columns()
+        .iter()
+        .map(|c| row.get_cell_value(c).unwrap_or(&empty))
+        .collect::<Vec<_>>();
+    validator.validate(&values)
+}
+
+/// Remove items which match a predicate and get them with their indices
+fn take_items<T>(items: &mut Vec<T>, predicate: impl Fn(&T) -> bool) -> Vec<(usize, T)> {
+    let mut taken = vec![];
+    let mut kept = vec![];
+    for (index, item) in std::mem::take(items).into_iter().enumerate() {
+        if predicate(&item) {
+            taken.push((index, item));
+        } else {
+            kept.push(item);
+        }
+    }
+    *items = kept;
+    taken
+}
+
+/// Put items which were taken by take_items back to their indices
+fn restore_items<T>(items: &mut Vec<T>, taken: Vec<(usize, T)>) {
+    for (index, item) in taken {
+        items.insert(index.min(items.len()), item);
+    }
+}
+
+/// Column which is removed from virtual data with everything attached to it
+///
+/// History keeps it to restore a deleted column.
+#[derive(Clone)]
+pub struct RemovedColumn {
+    column: Column,
+    meta: Meta,
+    values: Vec<Value>,
+    constraints: Vec<(usize, KeyConstraint)>,
+    row_validators: Vec<(usize, RowValidator)>,
+    foreign_keys: Vec<(usize, ForeignKey)>,
+    derived_columns: Vec<(usize, DerivedColumn)>,
+}
+
 /// to_string implementation for virtual data
 ///
 /// This returns csv value string
@@ -912,11 +2076,14 @@ impl std::fmt::Display for VirtualData {
 /// Column of virtual data
 ///
 /// Column is "text" type by default but can be further configured with value limiter.
+/// Description and unit are informative and don't affect values.
 #[derive(Clone, Debug)]
 pub struct Column {
     pub name: String,
     pub column_type: ValueType,
     pub limiter: ValueLimiter,
+    pub description: Option<String>,
+    pub unit: Option<String>,
 }
 
 impl Column {
@@ -926,6 +2093,8 @@ impl Column {
             name: name.to_string(),
             column_type: ValueType::Text,
             limiter: ValueLimiter::default(),
+            description: None,
+            unit: None,
         }
     }
 
@@ -935,6 +2104,8 @@ impl Column {
             name: name.to_string(),
             column_type,
             limiter: limiter.unwrap_or_default(),
+            description: None,
+            unit: None,
         }
     }
 
@@ -959,6 +2130,14 @@ impl Column {
         self.limiter = limiter;
     }
 
+    /// Attach a custom validator to a column's limiter
+    ///
+    /// This doesn't validate existing values. Use VirtualData's set_limiter to apply validator to
+    /// existing values.
+    pub fn set_validator(&mut self, validator: Validator) {
+        self.limiter.set_validator(validator);
+    }
+
     /// Get default value by column
     ///
     /// Every value type has it's own default value.